@@ -0,0 +1,87 @@
+//! Measures parsing throughput over a large generated document, both from
+//! memory and from an (unbuffered) file
+//!
+//! Run with `cargo bench`
+
+use std::{
+    fmt::Write,
+    fs::{self, File},
+    io,
+    time::{Duration, Instant},
+};
+
+use oxiyaml::Parser;
+
+const ENTRIES: usize = 20_000;
+const RUNS: u32 = 5;
+
+fn main() -> io::Result<()> {
+    let document = generate(ENTRIES);
+    let path = std::env::temp_dir().join(format!("oxiyaml-bench-{}.yaml", std::process::id()));
+    fs::write(&path, &document)?;
+
+    println!(
+        "document: {} entries, {:.2} MiB",
+        ENTRIES,
+        document.len() as f64 / (1024.0 * 1024.0)
+    );
+
+    bench("slice", document.len(), || {
+        count(Parser::from_slice(document.as_bytes()))
+    });
+    bench("file", document.len(), || {
+        count(Parser::from_reader(
+            File::open(&path).expect("open bench file"),
+        ))
+    });
+
+    fs::remove_file(&path)
+}
+
+/// Runs the given parse several times, reporting the best throughput
+fn bench(name: &str, len: usize, mut parse: impl FnMut() -> usize) {
+    let mut best = Duration::MAX;
+    let mut nodes = 0;
+
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        nodes = parse();
+        best = best.min(start.elapsed());
+    }
+
+    let throughput = len as f64 / (1024.0 * 1024.0) / best.as_secs_f64();
+    println!(
+        "{:>6}: {} nodes in {:?} ({:.2} MiB/s)",
+        name, nodes, best, throughput
+    );
+}
+
+fn count<R: io::Read>(parser: Parser<R>) -> usize {
+    parser.fold(0, |count, node| {
+        node.expect("bench document is valid");
+        count + 1
+    })
+}
+
+/// Generates a document exercising each of the node styles
+fn generate(entries: usize) -> String {
+    let mut document = String::new();
+
+    for i in 0..entries {
+        writeln!(document, "entry{}:", i).unwrap();
+        writeln!(document, "  name: item number {} # trailing comment", i).unwrap();
+        writeln!(document, "  version: 1.{}.{}", i % 10, i % 100).unwrap();
+        writeln!(document, "  path: /usr/share/item/{}", i).unwrap();
+        writeln!(document, "  quoted: \"escaped \\t value {}\"", i).unwrap();
+        writeln!(document, "  single: 'it''s {}'", i).unwrap();
+        writeln!(document, "  flow: {{a: {}, b: [x, y, z]}}", i).unwrap();
+        writeln!(document, "  tags:").unwrap();
+        writeln!(document, "  - first").unwrap();
+        writeln!(document, "  - second {}", i).unwrap();
+        writeln!(document, "  description: >").unwrap();
+        writeln!(document, "    a folded block scalar").unwrap();
+        writeln!(document, "    spanning two lines").unwrap();
+    }
+
+    document
+}
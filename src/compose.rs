@@ -0,0 +1,516 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::{
+    error::{Error, ErrorKind, Result},
+    node::{Node, NodeKind, ScalarStyle},
+    schema::{self, Resolved, Schema},
+};
+
+/// Expands aliases into copies of the nodes their anchor was given to.
+/// Anchors are scoped to the document they are given in, and can only
+/// be referred to once their node is complete
+pub(super) struct Aliases<'a, I> {
+    nodes: I,
+    anchors: HashMap<String, Vec<Node<'a>>>,
+    // Anchored nodes still being copied, with the depth they started at
+    recording: Vec<(String, usize, Vec<Node<'a>>)>,
+    // Copies still to be yielded, in reverse
+    replay: Vec<Node<'a>>,
+    // Collections the last node is in
+    depth: usize,
+}
+
+impl<'a, I> Aliases<'a, I>
+where
+    I: Iterator<Item = Result<Node<'a>>>,
+{
+    pub(super) fn new(nodes: I) -> Self {
+        Self {
+            nodes,
+            anchors: HashMap::new(),
+            recording: Vec::new(),
+            replay: Vec::new(),
+            depth: 0,
+        }
+    }
+
+    fn pull(&mut self) -> Option<Result<Node<'a>>> {
+        self.replay.pop().map(Ok).or_else(|| self.nodes.next())
+    }
+
+    /// Copies the node into every anchored node it is part of
+    fn record(&mut self, node: &Node<'a>) {
+        match node.kind {
+            NodeKind::DocumentStart(_) => self.anchors.clear(),
+            NodeKind::Comment(_) => return,
+            _ => {}
+        }
+
+        if let Some(anchor) = &node.anchor {
+            let anchor = anchor.to_string();
+            self.recording.push((anchor, self.depth, Vec::new()));
+        }
+
+        match node.kind {
+            NodeKind::MappingStart | NodeKind::SequenceStart => self.depth += 1,
+            NodeKind::MappingEnd | NodeKind::SequenceEnd => {
+                self.depth = self.depth.saturating_sub(1)
+            }
+            _ => {}
+        }
+
+        for (_, _, nodes) in self.recording.iter_mut() {
+            nodes.push(Node {
+                anchor: None,
+                ..node.clone()
+            });
+        }
+
+        while let Some((_, depth, _)) = self.recording.last() {
+            if *depth != self.depth {
+                break;
+            }
+
+            if let Some((anchor, _, nodes)) = self.recording.pop() {
+                self.anchors.insert(anchor, nodes);
+            }
+        }
+    }
+}
+
+impl<'a, I> Iterator for Aliases<'a, I>
+where
+    I: Iterator<Item = Result<Node<'a>>>,
+{
+    type Item = Result<Node<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = match self.pull()? {
+            Ok(Node {
+                kind: NodeKind::Alias(name),
+                mark,
+                ..
+            }) => match self.anchors.get(name.as_ref()) {
+                Some(nodes) => {
+                    self.replay.extend(nodes.iter().rev().cloned());
+                    self.replay.pop()?
+                }
+                None => {
+                    let err = ErrorKind::UnknownAnchor.with_context(name.as_ref());
+
+                    return Some(Err(Error::from(err).at(mark)));
+                }
+            },
+            Ok(node) => node,
+            Err(e) => return Some(Err(e)),
+        };
+
+        self.record(&node);
+
+        Some(Ok(node))
+    }
+}
+
+/// Counts the nodes aliases expand to, without expanding them. Streams
+/// which would expand to more nodes than allowed can then be refused as
+/// they are parsed
+#[derive(Debug)]
+pub(super) struct Expansions {
+    limit: usize,
+    // Nodes each complete anchored node expands to
+    anchors: HashMap<String, usize>,
+    // Anchored nodes still being counted, with the depth they started at
+    recording: Vec<(String, usize, usize)>,
+    // Collections the last node is in
+    depth: usize,
+    // Nodes the document's aliases have expanded to so far
+    total: usize,
+}
+
+impl Expansions {
+    pub(super) fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            anchors: HashMap::new(),
+            recording: Vec::new(),
+            depth: 0,
+            total: 0,
+        }
+    }
+
+    /// Counts the node, failing if the aliases of its document expand
+    /// to more nodes than the limit
+    pub(super) fn count(&mut self, node: &Node) -> Result<()> {
+        let size = match &node.kind {
+            NodeKind::DocumentStart(_) => {
+                *self = Self::new(self.limit);
+
+                return Ok(());
+            }
+            NodeKind::Comment(_) => return Ok(()),
+            NodeKind::Alias(name) => {
+                let size = self.anchors.get(name.as_ref()).copied().unwrap_or(0);
+                self.total = self.total.saturating_add(size);
+
+                if self.total > self.limit {
+                    let err = Error::from(ErrorKind::AliasLimit(self.limit));
+
+                    return Err(err.at(node.mark));
+                }
+
+                size
+            }
+            _ => 1,
+        };
+
+        if let Some(anchor) = &node.anchor {
+            self.recording.push((anchor.to_string(), self.depth, 0));
+        }
+
+        match node.kind {
+            NodeKind::MappingStart | NodeKind::SequenceStart => self.depth += 1,
+            NodeKind::MappingEnd | NodeKind::SequenceEnd => {
+                self.depth = self.depth.saturating_sub(1)
+            }
+            _ => {}
+        }
+
+        for (_, _, count) in self.recording.iter_mut() {
+            *count = count.saturating_add(size);
+        }
+
+        while let Some((_, depth, _)) = self.recording.last() {
+            if *depth != self.depth {
+                break;
+            }
+
+            if let Some((anchor, _, count)) = self.recording.pop() {
+                self.anchors.insert(anchor, count);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Applies merge keys, adding the entries of the mappings given as their
+/// value to the mapping holding them, unless it already has their key.
+/// Keys are merge keys if the schema resolves them to [`Resolved::Merge`]
+pub(super) struct Merges<'a, I, S> {
+    nodes: I,
+    schema: S,
+    // Collections the next node is in, innermost last
+    levels: Vec<Level<'a>>,
+    // Nodes ready to be yielded
+    ready: VecDeque<Node<'a>>,
+}
+
+/// A key's nodes and its value's
+type Entry<'a> = (Vec<Node<'a>>, Vec<Node<'a>>);
+
+#[derive(Debug, Default)]
+struct Level<'a> {
+    mapping: bool,
+    // Nodes finished in the collection, mappings alternate between keys and values
+    entries: usize,
+    // Keys given explicitly, which take precedence over merged ones
+    keys: Vec<Vec<Node<'a>>>,
+    // Nodes of the explicit key being read
+    key: Option<Vec<Node<'a>>>,
+    // Entries merged in, earlier ones take precedence
+    merged: Vec<Entry<'a>>,
+    // Nodes of a merge key's value, while they are read
+    merge: Option<Vec<Node<'a>>>,
+}
+
+impl Level<'_> {
+    fn is_key(&self) -> bool {
+        self.mapping && self.entries % 2 == 0
+    }
+}
+
+impl<'a, I, S> Merges<'a, I, S>
+where
+    I: Iterator<Item = Result<Node<'a>>>,
+    S: Schema,
+{
+    pub(super) fn new(nodes: I, schema: S) -> Self {
+        Self {
+            nodes,
+            schema,
+            levels: Vec::new(),
+            ready: VecDeque::new(),
+        }
+    }
+
+    fn process(&mut self, node: Node<'a>) -> Result<()> {
+        match node.kind {
+            NodeKind::MappingStart | NodeKind::SequenceStart => {
+                let mapping = node.kind == NodeKind::MappingStart;
+
+                if let Some(level) = self.levels.last_mut().filter(|level| level.is_key()) {
+                    level.key = Some(Vec::new());
+                }
+                self.emit(node);
+                self.levels.push(Level {
+                    mapping,
+                    ..Default::default()
+                });
+
+                Ok(())
+            }
+            NodeKind::MappingEnd | NodeKind::SequenceEnd => {
+                if let Some(Level { keys, merged, .. }) = self.levels.pop() {
+                    let schema = &self.schema;
+                    let merged: Vec<_> = merged
+                        .into_iter()
+                        .filter(|(key, _)| !keys.iter().any(|k| same_key(schema, k, key)))
+                        .flat_map(|(key, value)| key.into_iter().chain(value))
+                        .collect();
+
+                    merged.into_iter().for_each(|node| self.emit(node));
+                }
+
+                self.emit(node);
+                self.finish()
+            }
+            NodeKind::Comment(_) => {
+                if self.levels.iter().all(|level| level.merge.is_none()) {
+                    self.ready.push_back(node);
+                }
+
+                Ok(())
+            }
+            _ => {
+                let merge = self.is_merge(&node);
+
+                match self.levels.last_mut() {
+                    Some(level) if level.is_key() && merge => {
+                        level.entries += 1;
+                        level.merge = Some(Vec::new());
+
+                        return Ok(());
+                    }
+                    Some(level) if level.is_key() => level.key = Some(Vec::new()),
+                    _ => {}
+                }
+
+                self.emit(node);
+                self.finish()
+            }
+        }
+    }
+
+    /// Counts a finished node against its collection, taking the entries
+    /// of a merge key's value once it is complete
+    fn finish(&mut self) -> Result<()> {
+        let schema = &self.schema;
+        let level = match self.levels.last_mut() {
+            Some(level) => level,
+            None => return Ok(()),
+        };
+        level.entries += 1;
+
+        if let Some(key) = level.key.take() {
+            level.keys.push(key);
+        }
+
+        if let Some(nodes) = level.merge.take() {
+            for entry in merge_entries(nodes)? {
+                if !level
+                    .merged
+                    .iter()
+                    .any(|(key, _)| same_key(schema, key, &entry.0))
+                {
+                    level.merged.push(entry);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Passes on the node, unless it is part of a merge key's value.
+    /// Explicit keys being read keep a copy
+    fn emit(&mut self, node: Node<'a>) {
+        for key in self
+            .levels
+            .iter_mut()
+            .filter_map(|level| level.key.as_mut())
+        {
+            key.push(node.clone());
+        }
+
+        match self
+            .levels
+            .iter_mut()
+            .rev()
+            .find_map(|level| level.merge.as_mut())
+        {
+            Some(nodes) => nodes.push(node),
+            None => self.ready.push_back(node),
+        }
+    }
+
+    /// Whether the node is a merge key. Keys tagged with anything but
+    /// `!!merge` never are
+    fn is_merge(&self, node: &Node) -> bool {
+        match (&node.kind, node.tag.as_deref()) {
+            (NodeKind::Scalar { value, .. }, Some(tag)) if schema::is_merge_tag(tag) => {
+                value == "<<"
+            }
+            (
+                NodeKind::Scalar {
+                    value,
+                    style: ScalarStyle::Plain,
+                },
+                None,
+            ) => self.schema.resolve(value) == Resolved::Merge,
+            _ => false,
+        }
+    }
+}
+
+impl<'a, I, S> Iterator for Merges<'a, I, S>
+where
+    I: Iterator<Item = Result<Node<'a>>>,
+    S: Schema,
+{
+    type Item = Result<Node<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(node) = self.ready.pop_front() {
+                return Some(Ok(node));
+            }
+
+            let result = self.nodes.next()?.and_then(|node| self.process(node));
+
+            if let Err(e) = result {
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+/// Splits a merge key's value, a mapping or a sequence of
+/// mappings, into its entries
+fn merge_entries(nodes: Vec<Node>) -> Result<Vec<Entry>> {
+    let mut nodes = nodes.into_iter();
+    let mut entries = Vec::new();
+
+    match nodes.next() {
+        Some(node) if node.kind == NodeKind::MappingStart => {
+            mapping_entries(&mut nodes, &mut entries)
+        }
+        Some(node) if node.kind == NodeKind::SequenceStart => loop {
+            match nodes.next() {
+                Some(node) if node.kind == NodeKind::MappingStart => {
+                    mapping_entries(&mut nodes, &mut entries)
+                }
+                Some(node) if node.kind == NodeKind::SequenceEnd => break,
+                Some(node) => return Err(not_mergeable(&node)),
+                None => break,
+            }
+        },
+        Some(node) => return Err(not_mergeable(&node)),
+        None => {}
+    }
+
+    Ok(entries)
+}
+
+/// Reads a mapping's entries, up to its end
+fn mapping_entries<'a>(nodes: &mut impl Iterator<Item = Node<'a>>, entries: &mut Vec<Entry<'a>>) {
+    while let Some(key) = nodes
+        .next()
+        .filter(|node| node.kind != NodeKind::MappingEnd)
+    {
+        let key = subtree(key, nodes);
+        let value = match nodes.next() {
+            Some(value) => subtree(value, nodes),
+            None => break,
+        };
+
+        entries.push((key, value));
+    }
+}
+
+/// Reads the rest of the node starting with the given one
+fn subtree<'a>(first: Node<'a>, nodes: &mut impl Iterator<Item = Node<'a>>) -> Vec<Node<'a>> {
+    let mut depth = 0;
+    let mut tree = Vec::new();
+    let mut next = Some(first);
+
+    while let Some(node) = next {
+        match node.kind {
+            NodeKind::MappingStart | NodeKind::SequenceStart => depth += 1,
+            NodeKind::MappingEnd | NodeKind::SequenceEnd => depth -= 1,
+            _ => {}
+        }
+        tree.push(node);
+
+        next = match depth {
+            0 => None,
+            _ => nodes.next(),
+        };
+    }
+
+    tree
+}
+
+/// Whether the keys hold the same value, comparing their scalars as
+/// resolved by the schema rather than as written
+fn same_key<S: Schema>(schema: &S, a: &[Node], b: &[Node]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same_node(schema, a, b))
+}
+
+fn same_node<S: Schema>(schema: &S, a: &Node, b: &Node) -> bool {
+    // Only tags outside the schemas are kept with the value
+    let tag = |node: &Node| {
+        node.tag
+            .as_ref()
+            .filter(|tag| !schema::is_schema_tag(tag))
+            .map(|tag| tag.to_string())
+    };
+
+    if tag(a) != tag(b) {
+        return false;
+    }
+
+    match (&a.kind, &b.kind) {
+        (NodeKind::Scalar { value: x, .. }, NodeKind::Scalar { value: y, .. }) => {
+            match (resolve(schema, a), resolve(schema, b)) {
+                (Some(Resolved::Str), Some(Resolved::Str)) => x == y,
+                (Some(x), Some(y)) => x == y,
+                _ => false,
+            }
+        }
+        (x, y) => x == y,
+    }
+}
+
+/// Resolves a scalar the way it is read as a value, integers too large
+/// for an i64 are floats and merge keys plain strings
+fn resolve<S: Schema>(schema: &S, node: &Node) -> Option<Resolved> {
+    let (value, style) = match &node.kind {
+        NodeKind::Scalar { value, style } => (value, *style),
+        _ => return None,
+    };
+    let plain = style == ScalarStyle::Plain;
+
+    match schema::resolve_tagged(schema, node.tag.as_deref(), value, plain).ok()? {
+        Resolved::UInt(u) => Some(Resolved::Float(u as f64)),
+        Resolved::Merge => Some(Resolved::Str),
+        resolved => Some(resolved),
+    }
+}
+
+fn not_mergeable(node: &Node) -> Error {
+    let msg = format!(
+        "expected a mapping to merge, found {}",
+        node.kind.describe()
+    );
+
+    Error::from(ErrorKind::InvalidMerge.with_context(msg.as_str())).at(node.mark)
+}
@@ -0,0 +1,45 @@
+/// Options controlling how the parser treats its stream
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ParserConfig {
+    /// Emit comments as [`NodeKind::Comment`](crate::NodeKind::Comment)
+    /// nodes rather than skipping them
+    pub comments: bool,
+    /// Caps on what the stream may use, exceeding any is an error
+    pub limits: Limits,
+}
+
+/// Limits guarding against hostile streams, such as those whose aliases
+/// expand to far more nodes than the stream holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Nodes the aliases of a single document may expand to
+    pub aliases: usize,
+    /// Collections which may be open at once
+    pub depth: usize,
+    /// Bytes of a single scalar, comment or anchor name
+    pub scalar: usize,
+    /// Bytes of a single document, counted from the end of the last
+    pub document: usize,
+    /// Documents in the stream
+    pub documents: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            aliases: 1 << 20,
+            depth: 128,
+            scalar: 1 << 26,
+            document: 1 << 30,
+            documents: usize::MAX,
+        }
+    }
+}
+
+/// Options controlling how the emitter lays out its output
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EmitterConfig {
+    /// Write every collection in flow style, [a, b] and {k: v},
+    /// rather than only those which can't be written in block style
+    pub flow: bool,
+}
@@ -0,0 +1,800 @@
+use std::{borrow::Cow, fmt::Display, io};
+
+use serde::de::{
+    self,
+    value::{StringDeserializer, UnitDeserializer},
+    DeserializeOwned, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+
+use super::{
+    compose::{Aliases, Merges},
+    error::{Error, ErrorKind, Result},
+    mark::Mark,
+    node::{Node, NodeKind, ScalarStyle},
+    parser::Parser,
+    schema::{self, Core, Resolved, Schema},
+};
+
+/// Deserialize an instance of T from a string of YAML, borrowing
+/// from it where possible
+pub fn from_str<'de, T>(s: &'de str) -> Result<T>
+where
+    T: de::Deserialize<'de>,
+{
+    from_slice(s.as_bytes())
+}
+
+/// Deserialize an instance of T from a slice of YAML, borrowing
+/// from it where possible
+pub fn from_slice<'de, T>(slice: &'de [u8]) -> Result<T>
+where
+    T: de::Deserialize<'de>,
+{
+    from_nodes_with(Parser::from_slice(slice), Core)
+}
+
+/// Deserialize an instance of T from a stream of YAML
+pub fn from_reader<R, T>(reader: R) -> Result<T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    from_nodes_with(Parser::from_reader(reader), Core)
+}
+
+/// Deserialize an instance of T from the nodes of a single document
+/// stream, such as those of a configured [`Parser`], resolving plain
+/// scalars with the given schema
+pub fn from_nodes_with<'de, I, S, T>(nodes: I, schema: S) -> Result<T>
+where
+    I: IntoIterator<Item = Result<Node<'de>>>,
+    S: Schema,
+    T: de::Deserialize<'de>,
+{
+    Deserializer {
+        nodes: Merges::new(Aliases::new(nodes.into_iter()), &schema),
+        schema: &schema,
+        peeked: None,
+        mark: Mark::default(),
+    }
+    .document()
+}
+
+/// Drives the parser, feeding its nodes to serde one at a time
+struct Deserializer<'de, N, S> {
+    nodes: N,
+    schema: S,
+    peeked: Option<Node<'de>>,
+    // Position of the last node pulled from the parser
+    mark: Mark,
+}
+
+impl<'de, N, S> Deserializer<'de, N, S>
+where
+    N: Iterator<Item = Result<Node<'de>>>,
+    S: Schema,
+{
+    /// Deserializes the stream's document, an empty stream
+    /// is treated as a single null node
+    fn document<T>(mut self) -> Result<T>
+    where
+        T: de::Deserialize<'de>,
+    {
+        self.expect(NodeKind::StreamStart)?;
+
+        let value = match self.peek()?.kind {
+            NodeKind::DocumentStart(_) => {
+                self.next()?;
+                let value = T::deserialize(&mut self)?;
+                self.expect(NodeKind::DocumentEnd)?;
+
+                value
+            }
+            _ => T::deserialize(UnitDeserializer::<Error>::new()).map_err(|e| e.at(self.mark))?,
+        };
+
+        self.expect(NodeKind::StreamEnd)?;
+
+        Ok(value)
+    }
+
+    fn peek(&mut self) -> Result<&Node<'de>> {
+        let node = match self.peeked.take() {
+            Some(node) => node,
+            None => self.pull()?,
+        };
+
+        Ok(self.peeked.get_or_insert(node))
+    }
+
+    fn next(&mut self) -> Result<Node<'de>> {
+        match self.peeked.take() {
+            Some(node) => Ok(node),
+            None => self.pull(),
+        }
+    }
+
+    fn pull(&mut self) -> Result<Node<'de>> {
+        loop {
+            match self.nodes.next() {
+                Some(Ok(Node {
+                    kind: NodeKind::Comment(_),
+                    ..
+                })) => continue,
+                Some(Ok(node)) => {
+                    self.mark = node.mark;
+
+                    return Ok(node);
+                }
+                Some(Err(e)) => return Err(e),
+                None => return Err(Error::from(ErrorKind::InvalidEOF).at(self.mark)),
+            }
+        }
+    }
+
+    fn expect(&mut self, kind: NodeKind) -> Result<()> {
+        let node = self.next()?;
+
+        if node.kind == kind {
+            Ok(())
+        } else {
+            Err(unexpected(&node.kind, kind.describe()).at(node.mark))
+        }
+    }
+
+    /// Whether the next node is a plain scalar resolving to null
+    fn peek_null(&mut self) -> Result<bool> {
+        self.peek()?;
+
+        let null = match &self.peeked {
+            Some(Node {
+                kind: NodeKind::Scalar { value, style },
+                tag,
+                ..
+            }) => {
+                let plain = *style == ScalarStyle::Plain;
+                let resolved = schema::resolve_tagged(&self.schema, tag.as_deref(), value, plain);
+
+                matches!(resolved, Ok(Resolved::Null))
+            }
+            _ => false,
+        };
+
+        Ok(null)
+    }
+
+    /// Hands the given node, and any it contains to the visitor. Tags
+    /// the schemas don't resolve are left for [`deserialize_enum`] to read
+    ///
+    /// [`deserialize_enum`]: de::Deserializer::deserialize_enum
+    fn visit_node<V>(&mut self, node: Node<'de>, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let (mark, tag) = (node.mark, node.tag.as_deref());
+
+        match node.kind {
+            NodeKind::Scalar { value, style } => {
+                let plain = style == ScalarStyle::Plain;
+                let resolved = schema::resolve_tagged(&self.schema, tag, &value, plain)?;
+
+                visit_scalar(resolved, value, visitor)
+            }
+            NodeKind::SequenceStart => {
+                let value = schema::check_collection(tag, false)
+                    .and_then(|_| visitor.visit_seq(Seq { de: &mut *self }));
+
+                value.and_then(|value| self.expect(NodeKind::SequenceEnd).and(Ok(value)))
+            }
+            NodeKind::MappingStart => {
+                let value = schema::check_collection(tag, true)
+                    .and_then(|_| visitor.visit_map(Map { de: &mut *self }));
+
+                value.and_then(|value| self.expect(NodeKind::MappingEnd).and(Ok(value)))
+            }
+            kind => Err(unexpected(&kind, "a node")),
+        }
+        .map_err(|e| e.at(mark))
+    }
+}
+
+impl<'de, N, S> de::Deserializer<'de> for &mut Deserializer<'de, N, S>
+where
+    N: Iterator<Item = Result<Node<'de>>>,
+    S: Schema,
+{
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let node = self.next()?;
+
+        self.visit_node(node, visitor)
+    }
+
+    /// Any scalar can be read as a string, regardless of what
+    /// it would otherwise resolve to
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let node = self.next()?;
+        let mark = node.mark;
+
+        match node.kind {
+            NodeKind::Scalar { value, .. } => visit_str(value, visitor).map_err(|e| e.at(mark)),
+            kind => self.visit_node(kind.at(mark), visitor),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        if self.peek_null()? {
+            self.next()?;
+            visitor.visit_none()
+        } else {
+            visitor.visit_some(self)
+        }
+    }
+
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Unit variants are plain scalars, any other variant is
+    /// a mapping of its name to its content. A node with a local
+    /// tag, such as '!Variant', is instead the content of the
+    /// variant the tag names
+    fn deserialize_enum<V>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let node = self.next()?;
+        let mark = node.mark;
+
+        if let Some(variant) = node.tag.as_deref().and_then(local_tag) {
+            let variant = variant.to_owned();
+            self.peeked = Some(Node { tag: None, ..node });
+
+            return visitor
+                .visit_enum(TaggedEnum { de: self, variant })
+                .map_err(|e| e.at(mark));
+        }
+
+        match node.kind {
+            NodeKind::Scalar { value, .. } => visitor.visit_enum(value.into_deserializer()),
+            NodeKind::MappingStart => visitor
+                .visit_enum(Enum { de: &mut *self })
+                .and_then(|value| self.expect(NodeKind::MappingEnd).and(Ok(value))),
+            kind => Err(unexpected(&kind, "an enum")),
+        }
+        .map_err(|e| e.at(mark))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct ignored_any
+    }
+}
+
+struct Seq<'a, 'de, N, S> {
+    de: &'a mut Deserializer<'de, N, S>,
+}
+
+impl<'de, 'a, N, S> SeqAccess<'de> for Seq<'a, 'de, N, S>
+where
+    N: Iterator<Item = Result<Node<'de>>>,
+    S: Schema,
+{
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        match self.de.peek()?.kind {
+            NodeKind::SequenceEnd => Ok(None),
+            _ => seed.deserialize(&mut *self.de).map(Some),
+        }
+    }
+}
+
+struct Map<'a, 'de, N, S> {
+    de: &'a mut Deserializer<'de, N, S>,
+}
+
+impl<'de, 'a, N, S> MapAccess<'de> for Map<'a, 'de, N, S>
+where
+    N: Iterator<Item = Result<Node<'de>>>,
+    S: Schema,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match self.de.peek()?.kind {
+            NodeKind::MappingEnd => Ok(None),
+            _ => seed.deserialize(&mut *self.de).map(Some),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        seed.deserialize(&mut *self.de)
+    }
+}
+
+/// An externally tagged variant, the single key of a mapping
+struct Enum<'a, 'de, N, S> {
+    de: &'a mut Deserializer<'de, N, S>,
+}
+
+impl<'de, 'a, N, S> EnumAccess<'de> for Enum<'a, 'de, N, S>
+where
+    N: Iterator<Item = Result<Node<'de>>>,
+    S: Schema,
+{
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant = seed.deserialize(&mut *self.de)?;
+
+        Ok((variant, self))
+    }
+}
+
+impl<'de, 'a, N, S> VariantAccess<'de> for Enum<'a, 'de, N, S>
+where
+    N: Iterator<Item = Result<Node<'de>>>,
+    S: Schema,
+{
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        de::Deserialize::deserialize(self.de)
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V>(self, _len: usize, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_seq(self.de, visitor)
+    }
+
+    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        de::Deserializer::deserialize_map(self.de, visitor)
+    }
+}
+
+/// A variant named by its content's local tag
+struct TaggedEnum<'a, 'de, N, S> {
+    de: &'a mut Deserializer<'de, N, S>,
+    variant: String,
+}
+
+impl<'de, 'a, N, S> EnumAccess<'de> for TaggedEnum<'a, 'de, N, S>
+where
+    N: Iterator<Item = Result<Node<'de>>>,
+    S: Schema,
+{
+    type Error = Error;
+    type Variant = Enum<'a, 'de, N, S>;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let variant: StringDeserializer<Error> = self.variant.into_deserializer();
+        let variant = seed.deserialize(variant)?;
+
+        Ok((variant, Enum { de: self.de }))
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        ErrorKind::Message(msg.to_string().into()).into()
+    }
+}
+
+fn visit_str<'de, V>(value: Cow<'de, str>, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    match value {
+        Cow::Borrowed(s) => visitor.visit_borrowed_str(s),
+        Cow::Owned(s) => visitor.visit_string(s),
+    }
+}
+
+fn visit_scalar<'de, V>(resolved: Resolved, value: Cow<'de, str>, visitor: V) -> Result<V::Value>
+where
+    V: Visitor<'de>,
+{
+    match resolved {
+        Resolved::Null => visitor.visit_unit(),
+        Resolved::Bool(b) => visitor.visit_bool(b),
+        Resolved::Int(i) => visitor.visit_i64(i),
+        Resolved::UInt(u) => visitor.visit_u64(u),
+        Resolved::Float(f) => visitor.visit_f64(f),
+        Resolved::Str | Resolved::Merge => visit_str(value, visitor),
+    }
+}
+
+/// The name of a local tag, one starting with '!'
+fn local_tag(tag: &str) -> Option<&str> {
+    tag.strip_prefix('!').filter(|name| !name.is_empty())
+}
+
+fn unexpected(kind: &NodeKind, expected: &str) -> Error {
+    de::Error::custom(format_args!(
+        "expected {}, found {}",
+        expected,
+        kind.describe()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Config<'a> {
+        name: &'a str,
+        version: u32,
+        ratio: f64,
+        enabled: bool,
+        tags: Vec<String>,
+        owner: Option<String>,
+        limits: BTreeMap<String, i64>,
+        mode: Mode,
+        id: Id,
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Mode {
+        Fast,
+        Slow,
+        Custom(u8),
+        Range(u8, u8),
+        Named { label: String },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Id(String);
+
+    #[test]
+    fn struct_nested() -> Result<()> {
+        let data = "name: oxiyaml
+version: 3
+ratio: .5
+enabled: true
+tags: [a, 'b', \"c\"]
+owner: ~
+limits:
+  depth: 0x10
+  bytes: -1
+mode: Fast
+id: 42
+";
+
+        let config: Config = from_str(data)?;
+
+        assert_eq!(
+            config,
+            Config {
+                name: "oxiyaml",
+                version: 3,
+                ratio: 0.5,
+                enabled: true,
+                tags: vec!["a".into(), "b".into(), "c".into()],
+                owner: None,
+                limits: vec![("depth".into(), 16), ("bytes".into(), -1)]
+                    .into_iter()
+                    .collect(),
+                mode: Mode::Fast,
+                id: Id("42".into()),
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn enum_tagged() -> Result<()> {
+        let data = "- Slow\n- Custom: 7\n- Range: [1, 2]\n- Named:\n    label: x\n";
+
+        assert_eq!(
+            from_str::<Vec<Mode>>(data)?,
+            vec![
+                Mode::Slow,
+                Mode::Custom(7),
+                Mode::Range(1, 2),
+                Mode::Named { label: "x".into() }
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn enum_local_tags() -> Result<()> {
+        let data = "- !Slow\n- !Custom 7\n- !Range [1, 2]\n- !Named\n  label: x\n- Fast\n";
+
+        assert_eq!(
+            from_str::<Vec<Mode>>(data)?,
+            vec![
+                Mode::Slow,
+                Mode::Custom(7),
+                Mode::Range(1, 2),
+                Mode::Named { label: "x".into() },
+                Mode::Fast,
+            ]
+        );
+        assert!(from_str::<Mode>("!Unknown 7\n").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn scalar_tags() -> Result<()> {
+        #[derive(Debug, PartialEq, Deserialize)]
+        #[serde(untagged)]
+        enum Scalar {
+            Int(i64),
+            Str(String),
+        }
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Tags {
+            id: Scalar,
+            missing: Option<String>,
+            ratio: f64,
+        }
+
+        let data = "id: !!str 123\nmissing: !!str null\nratio: !!float 2\n";
+
+        assert_eq!(
+            from_str::<Tags>(data)?,
+            Tags {
+                id: Scalar::Str("123".into()),
+                missing: Some("null".into()),
+                ratio: 2.0,
+            }
+        );
+
+        let err = from_str::<u8>("!!int x\n").unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidTag));
+
+        Ok(())
+    }
+
+    #[test]
+    fn map_complex_keys() -> Result<()> {
+        let data = "? [a, b]\n: first\n? - c\n: second\n";
+        let map = from_str::<BTreeMap<Vec<String>, String>>(data)?;
+
+        assert_eq!(
+            map,
+            vec![
+                (vec!["a".into(), "b".into()], "first".into()),
+                (vec!["c".into()], "second".into()),
+            ]
+            .into_iter()
+            .collect()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn scalar_resolved() -> Result<()> {
+        #[derive(Debug, PartialEq, Deserialize)]
+        #[serde(untagged)]
+        enum Any {
+            Null(()),
+            Bool(bool),
+            Int(i64),
+            Float(f64),
+            Str(String),
+        }
+
+        let data = "[~, null, '', FALSE, 0o17, +12, 1e3, -.inf, 1.5.5, 0x, .]";
+
+        assert_eq!(
+            from_str::<Vec<Any>>(data)?,
+            vec![
+                Any::Null(()),
+                Any::Null(()),
+                Any::Str("".into()),
+                Any::Bool(false),
+                Any::Int(15),
+                Any::Int(12),
+                Any::Float(1000.0),
+                Any::Float(f64::NEG_INFINITY),
+                Any::Str("1.5.5".into()),
+                Any::Str("0x".into()),
+                Any::Str(".".into()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn scalar_schema() -> Result<()> {
+        use crate::{Json, Parser, Yaml11};
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Legacy {
+            enabled: bool,
+            owner: Option<String>,
+        }
+
+        let data = "enabled: yes\nowner: ~\n";
+
+        assert_eq!(
+            from_nodes_with::<_, _, Legacy>(Parser::from_str(data), Yaml11)?,
+            Legacy {
+                enabled: true,
+                owner: None
+            }
+        );
+        assert!(from_str::<Legacy>(data).is_err());
+        assert_eq!(
+            from_nodes_with::<_, _, Legacy>(Parser::from_str("enabled: true\nowner: ~"), Json)?
+                .owner,
+            Some("~".into())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn aliases_merge() -> Result<()> {
+        use crate::{Parser, Yaml11};
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Job<'a> {
+            image: &'a str,
+            retries: u8,
+            #[serde(borrow)]
+            script: Vec<&'a str>,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Pipeline<'a> {
+            #[serde(borrow)]
+            build: Job<'a>,
+            #[serde(borrow)]
+            test: Job<'a>,
+        }
+
+        let data = include_str!("../testing/data/anchors.yaml");
+        let pipeline: Pipeline = from_nodes_with(Parser::from_str(data), Yaml11)?;
+
+        assert_eq!(pipeline.build.retries, 2);
+        assert_eq!(
+            pipeline.test,
+            Job {
+                image: "rust:latest",
+                retries: 0,
+                script: vec!["cargo test"],
+            }
+        );
+
+        let err = from_str::<Vec<u8>>("- *a\n").unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::UnknownAnchor));
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_empty() -> Result<()> {
+        assert_eq!(from_str::<Option<u8>>("")?, None);
+        assert_eq!(
+            from_reader::<_, Option<u8>>("# nothing\n".as_bytes())?,
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reader_owned() -> Result<()> {
+        let map: BTreeMap<String, Vec<u8>> = from_reader("a: [1, 2]\nb: []\n".as_bytes())?;
+
+        assert_eq!(map["a"], vec![1, 2]);
+        assert!(map["b"].is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_position() {
+        let err = from_str::<BTreeMap<String, u8>>("a: 1\nb: '2'\n").unwrap_err();
+
+        assert!(matches!(err.kind(), ErrorKind::Message(_)));
+        assert_eq!(err.mark().map(|m| (m.line, m.column)), Some((1, 3)));
+
+        let err = from_str::<Mode>("Custom: 1\nSlow: ~\n").unwrap_err();
+
+        assert_eq!(err.mark().map(|m| m.line), Some(1));
+
+        #[derive(Debug, Deserialize)]
+        struct Inner {
+            #[allow(dead_code)]
+            s: String,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct Outer {
+            #[allow(dead_code)]
+            inner: Inner,
+        }
+
+        let err = from_str::<Outer>("a: 1\ninner:\n  x: 1").unwrap_err();
+
+        assert!(matches!(err.kind(), ErrorKind::Message(_)));
+        assert_eq!(err.mark().map(|m| (m.line, m.column)), Some((2, 2)));
+
+        let err = from_str::<Mode>("Named: {}\n").unwrap_err();
+
+        assert_eq!(err.mark().map(|m| (m.line, m.column)), Some((0, 7)));
+    }
+}
@@ -0,0 +1,129 @@
+use std::io;
+
+use super::{
+    error::{Error, Result},
+    node::Node,
+    schema::{Core, Schema},
+    value::Value,
+    Handle,
+};
+
+/// The nodes of a single document, from its start to its end. Any
+/// comments preceding the document's start are kept in front of it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Document<'a> {
+    nodes: Vec<Node<'a>>,
+}
+
+impl<'a> Document<'a> {
+    pub fn nodes(&self) -> &[Node<'a>] {
+        &self.nodes
+    }
+
+    pub fn into_nodes(self) -> Vec<Node<'a>> {
+        self.nodes
+    }
+
+    /// Builds the document's value, with plain scalars resolved under
+    /// the core schema
+    pub fn into_value(self) -> Result<Value> {
+        self.into_value_with(Core)
+    }
+
+    /// Builds the document's value, resolving plain scalars with the
+    /// given schema
+    pub fn into_value_with<S: Schema>(self, schema: S) -> Result<Value> {
+        Value::from_document(self.nodes, schema)
+    }
+}
+
+/// Iterator over the documents of a stream, see
+/// [`Parser::documents`](crate::Parser::documents). Comments following
+/// the last document are dropped
+pub struct Documents<'a, R> {
+    handle: Handle<'a, R>,
+}
+
+impl<'a, R> Documents<'a, R> {
+    pub(super) fn new(handle: Handle<'a, R>) -> Self {
+        Self { handle }
+    }
+
+    /// Problems the parser has continued past so far, see
+    /// [`Parser::warnings`](crate::Parser::warnings)
+    pub fn warnings(&self) -> &[Error] {
+        &self.handle.warnings
+    }
+}
+
+impl<'a, R> Iterator for Documents<'a, R>
+where
+    R: io::Read,
+{
+    type Item = Result<Document<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.handle
+            .next_document()
+            .map(|nodes| nodes.map(|nodes| Document { nodes }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{node::NodeKind, parser::Parser, schema::Failsafe, ParserConfig};
+
+    #[test]
+    fn documents_values() -> Result<()> {
+        let data = include_str!("../testing/data/documents.yaml");
+        let values = Parser::from_str(data)
+            .documents()
+            .map(|document| document?.into_value())
+            .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(values.len(), 5);
+        assert_eq!(values[0]["kind"], "Namespace");
+        assert_eq!(values[1]["spec"]["replicas"], 2);
+        assert_eq!(values[2], "literal\n");
+        assert_eq!(values[3], Value::Null);
+        assert_eq!(values[4][1], "b");
+
+        Ok(())
+    }
+
+    #[test]
+    fn documents_nodes() -> Result<()> {
+        let config = ParserConfig {
+            comments: true,
+            ..Default::default()
+        };
+        let data = "# first\na\n--- 1\n# trailing\n";
+        let documents = Parser::from_str_with_config(data, config)
+            .documents()
+            .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(documents.len(), 2);
+        assert_eq!(
+            documents[0].nodes(),
+            [
+                NodeKind::Comment(" first".into()),
+                NodeKind::DocumentStart(Default::default()),
+                NodeKind::scalar("a", crate::ScalarStyle::Plain),
+                NodeKind::DocumentEnd,
+            ]
+        );
+        assert_eq!(documents[1].clone().into_value_with(Failsafe)?, "1");
+
+        Ok(())
+    }
+
+    #[test]
+    fn documents_error() {
+        let mut documents = Parser::from_str("a\n---\n[b\n").documents();
+
+        assert!(documents.next().is_some_and(|document| document.is_ok()));
+        assert!(documents.next().is_some_and(|document| document.is_err()));
+        assert!(documents.next().is_none());
+    }
+}
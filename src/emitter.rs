@@ -0,0 +1,776 @@
+use std::io;
+
+use super::{
+    config::EmitterConfig,
+    error::{ErrorKind, Result},
+    node::{Directives, Node, NodeKind, ScalarStyle},
+    scanner::LevelKind,
+    states::{is_indicator, is_plain_safe, is_printable, is_uri_char, is_whitespace},
+};
+
+/// YAML emitter, writes a stream of nodes back out as YAML text.
+/// Nodes are expected in the order the parser yields them
+pub struct Emitter<W> {
+    writer: W,
+    config: EmitterConfig,
+    // Collections which have been started but not yet ended
+    stack: Vec<Level>,
+    // Comments waiting for the end of the current line
+    comments: Vec<String>,
+    documents: usize,
+    // Directives of the current document, which its tags are shortened with
+    directives: Directives,
+    // Whether anything has been written to the current line
+    line: bool,
+}
+
+/// An open collection
+#[derive(Debug)]
+struct Level {
+    kind: LevelKind,
+    flow: bool,
+    // Column the collection's entries start at, if in block style
+    indent: usize,
+    // Nodes written so far, mappings alternate between keys and values
+    entries: usize,
+    // Whether the first entry continues the line the collection started on
+    compact: bool,
+    // Whether an empty collection must be spaced from the line's content
+    spaced: bool,
+    // Whether the current key is a collection, given with '?' so its
+    // value must follow on a line of its own
+    explicit: bool,
+}
+
+impl Level {
+    fn is_key(&self) -> bool {
+        self.kind == LevelKind::Mapping && self.entries % 2 == 0
+    }
+}
+
+impl<W> Emitter<W>
+where
+    W: io::Write,
+{
+    /// Create an emitter which writes to the given stream
+    pub fn new(writer: W) -> Self {
+        Self::with_config(writer, Default::default())
+    }
+
+    /// Create an emitter which writes to the given stream,
+    /// configured by the given options
+    pub fn with_config(writer: W, config: EmitterConfig) -> Self {
+        Self {
+            writer,
+            config,
+            stack: Vec::new(),
+            comments: Vec::new(),
+            documents: 0,
+            directives: Directives::default(),
+            line: false,
+        }
+    }
+
+    /// Writes the given node, along with its anchor and tag
+    pub fn emit(&mut self, node: &Node) -> Result<()> {
+        match &node.kind {
+            NodeKind::StreamStart => Ok(()),
+            NodeKind::StreamEnd => {
+                self.end_line()?;
+                self.writer.flush()?;
+
+                Ok(())
+            }
+            NodeKind::DocumentStart(directives) => {
+                // Directives can't follow a document without an end marker
+                if self.documents > 0 && !directives.is_empty() {
+                    self.write("...")?;
+                    self.newline()?;
+                }
+
+                if let Some(version) = directives.version {
+                    writeln!(self.writer, "%YAML {}", version)?;
+                }
+
+                for (handle, prefix) in &directives.tags {
+                    writeln!(self.writer, "%TAG {} {}", handle, prefix)?;
+                }
+
+                if self.documents > 0 || !directives.is_empty() {
+                    self.write("---")?;
+                }
+                self.documents += 1;
+                self.directives = directives.clone();
+
+                Ok(())
+            }
+            NodeKind::DocumentEnd => match self.stack.is_empty() {
+                true => self.end_line(),
+                false => Err(ErrorKind::StateViolation.with_context("unterminated collection"))?,
+            },
+            NodeKind::MappingStart => self.open(LevelKind::Mapping, node),
+            NodeKind::SequenceStart => self.open(LevelKind::Sequence, node),
+            NodeKind::MappingEnd => self.close(LevelKind::Mapping),
+            NodeKind::SequenceEnd => self.close(LevelKind::Sequence),
+            NodeKind::Scalar { value, style } => {
+                self.begin(false)?;
+
+                if self.properties(node)? {
+                    self.write(" ")?;
+                }
+                self.scalar(value, *style)?;
+                self.entry();
+
+                Ok(())
+            }
+            NodeKind::Alias(_) if node.anchor.is_some() || node.tag.is_some() => {
+                Err(ErrorKind::StateViolation.with_context("an alias can't have properties"))?
+            }
+            NodeKind::Alias(name) => {
+                self.begin(false)?;
+                self.write("*")?;
+                self.write(name)?;
+                self.entry();
+
+                Ok(())
+            }
+            NodeKind::Comment(text) => {
+                self.comments.push(text.to_string());
+
+                // The comment ends the line, so entries can no longer follow on it
+                if let Some(level) = self.stack.last_mut() {
+                    level.compact = false;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Consumes the emitter, returning the underlying stream
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Starts a collection, which is written in flow style if configured
+    /// to, if it is inside a flow collection, or if it is a mapping key
+    fn open(&mut self, kind: LevelKind, node: &Node) -> Result<()> {
+        let flow = self.config.flow
+            || self
+                .stack
+                .last()
+                .is_some_and(|level| level.flow || level.is_key());
+        let properties = node.anchor.is_some() || node.tag.is_some();
+
+        // Properties share the line of the collection's key or entry
+        self.begin(!flow && !properties)?;
+
+        // Block mappings can only have collections as keys explicitly
+        if let Some(parent) = self.stack.last_mut() {
+            if !parent.flow && parent.is_key() {
+                parent.explicit = true;
+                self.write("? ")?;
+            }
+        }
+
+        let level = match self.stack.last() {
+            Some(parent) if parent.kind == LevelKind::Sequence => Level {
+                kind,
+                flow,
+                indent: parent.indent + 2,
+                entries: 0,
+                compact: true,
+                spaced: false,
+                explicit: false,
+            },
+            Some(parent) => Level {
+                kind,
+                flow,
+                indent: parent.indent + 2,
+                entries: 0,
+                compact: false,
+                spaced: true,
+                explicit: false,
+            },
+            None => Level {
+                kind,
+                flow,
+                indent: 0,
+                entries: 0,
+                compact: false,
+                spaced: self.line,
+                explicit: false,
+            },
+        };
+
+        // A block collection's entries follow its properties on a new line
+        let level = match self.properties(node)? {
+            true if flow => {
+                self.write(" ")?;
+                level
+            }
+            true => Level {
+                compact: false,
+                spaced: true,
+                ..level
+            },
+            false => level,
+        };
+
+        if flow {
+            self.write(Self::brackets(kind).0)?;
+        }
+        self.stack.push(level);
+
+        Ok(())
+    }
+
+    /// Ends the innermost collection, block collections without
+    /// entries can only be written in flow style
+    fn close(&mut self, kind: LevelKind) -> Result<()> {
+        let level = match self.stack.pop() {
+            // Mappings can't end between a key and its value
+            Some(level)
+                if level.kind == kind && (kind == LevelKind::Sequence || level.is_key()) =>
+            {
+                level
+            }
+            _ => Err(ErrorKind::StateViolation.with_context("unbalanced collection end"))?,
+        };
+        let (open, close) = Self::brackets(kind);
+
+        if level.flow {
+            self.write(close)?;
+        } else if level.entries == 0 {
+            if level.spaced {
+                self.write(" ")?;
+            }
+            self.write(open)?;
+            self.write(close)?;
+        }
+        self.entry();
+
+        Ok(())
+    }
+
+    /// Writes whatever must precede the next node in its collection. Block
+    /// collections don't share a line with their parent mapping's key
+    fn begin(&mut self, block: bool) -> Result<()> {
+        let level = match self.stack.last() {
+            Some(level) => level,
+            None if self.line && !block => return self.write(" "),
+            None => return Ok(()),
+        };
+        let (flow, indent, first, explicit) = (
+            level.flow,
+            level.indent,
+            level.compact && level.entries == 0,
+            level.explicit,
+        );
+
+        match (level.kind, level.is_key()) {
+            (_, _) if flow => match level.entries {
+                0 => Ok(()),
+                n if n % 2 == 1 && level.kind == LevelKind::Mapping => self.write(": "),
+                _ => self.write(", "),
+            },
+            (LevelKind::Sequence, _) => {
+                if !first {
+                    self.start_line(indent)?;
+                }
+                self.write("- ")
+            }
+            (LevelKind::Mapping, true) => match first {
+                true => Ok(()),
+                false => self.start_line(indent),
+            },
+            (LevelKind::Mapping, false) => {
+                if explicit {
+                    self.start_line(indent)?;
+
+                    if let Some(level) = self.stack.last_mut() {
+                        level.explicit = false;
+                    }
+                }
+
+                match block {
+                    true => self.write(":"),
+                    false => self.write(": "),
+                }
+            }
+        }
+    }
+
+    /// Writes the node's anchor and tag, returning whether it had either
+    fn properties(&mut self, node: &Node) -> Result<bool> {
+        if let Some(anchor) = &node.anchor {
+            self.write("&")?;
+            self.write(anchor)?;
+        }
+
+        if let Some(tag) = &node.tag {
+            if node.anchor.is_some() {
+                self.write(" ")?;
+            }
+
+            let tag = self.shorthand(tag);
+            self.write(&tag)?;
+        }
+
+        Ok(node.anchor.is_some() || node.tag.is_some())
+    }
+
+    /// Shortens the given tag with the handle standing for its prefix,
+    /// otherwise writing it verbatim
+    fn shorthand(&self, tag: &str) -> String {
+        if tag == "!" {
+            return tag.into();
+        }
+
+        let handles = self
+            .directives
+            .tags
+            .iter()
+            .map(|(handle, _)| handle.as_str());
+        let shorthand = handles.chain(["!!", "!"]).find_map(|handle| {
+            let prefix = self.directives.prefix(handle)?;
+
+            match tag.strip_prefix(prefix) {
+                Some(suffix) if !suffix.is_empty() => {
+                    Some(format!("{}{}", handle, encode_uri(suffix, b"!,[]{}")))
+                }
+                _ => None,
+            }
+        });
+
+        shorthand.unwrap_or_else(|| format!("!<{}>", encode_uri(tag, b"")))
+    }
+
+    /// Counts a finished node against its collection
+    fn entry(&mut self) {
+        if let Some(level) = self.stack.last_mut() {
+            level.entries += 1;
+        }
+    }
+
+    /// Writes the given scalar, quoting it only if it would
+    /// otherwise not be read back as the same plain scalar
+    fn scalar(&mut self, value: &str, style: ScalarStyle) -> Result<()> {
+        let flow = self.stack.last().is_some_and(|level| level.flow);
+
+        let style = match style {
+            ScalarStyle::DoubleQuoted => ScalarStyle::DoubleQuoted,
+            ScalarStyle::SingleQuoted if value.bytes().all(is_printable) => {
+                ScalarStyle::SingleQuoted
+            }
+            ScalarStyle::SingleQuoted => ScalarStyle::DoubleQuoted,
+            _ if is_plain(value, flow) => ScalarStyle::Plain,
+            _ if value.bytes().all(is_printable) => ScalarStyle::SingleQuoted,
+            _ => ScalarStyle::DoubleQuoted,
+        };
+
+        match style {
+            ScalarStyle::SingleQuoted => {
+                self.write("'")?;
+                self.write(&value.replace('\'', "''"))?;
+                self.write("'")
+            }
+            ScalarStyle::DoubleQuoted => {
+                self.write("\"")?;
+                self.write(&escape(value))?;
+                self.write("\"")
+            }
+            _ => self.write(value),
+        }
+    }
+
+    /// Writes any pending comments, then a line break
+    /// and the given indentation
+    fn start_line(&mut self, indent: usize) -> Result<()> {
+        self.end_line()?;
+
+        for _ in 0..indent {
+            self.writer.write_all(b" ")?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes any pending comments, ending the current line
+    /// if anything has been written to it
+    fn end_line(&mut self) -> Result<()> {
+        let indent = self
+            .stack
+            .iter()
+            .rev()
+            .find(|level| !level.flow)
+            .map_or(0, |level| level.indent);
+
+        for comment in std::mem::take(&mut self.comments) {
+            if self.line {
+                self.writer.write_all(b" ")?;
+            } else {
+                (0..indent).try_for_each(|_| self.writer.write_all(b" "))?;
+            }
+            write!(self.writer, "#{}", comment)?;
+            self.newline()?;
+        }
+
+        if self.line {
+            self.newline()?;
+        }
+
+        Ok(())
+    }
+
+    fn newline(&mut self) -> Result<()> {
+        self.writer.write_all(b"\n")?;
+        self.line = false;
+
+        Ok(())
+    }
+
+    fn write(&mut self, s: &str) -> Result<()> {
+        self.writer.write_all(s.as_bytes())?;
+        self.line = true;
+
+        Ok(())
+    }
+
+    fn brackets(kind: LevelKind) -> (&'static str, &'static str) {
+        match kind {
+            LevelKind::Mapping => ("{", "}"),
+            LevelKind::Sequence => ("[", "]"),
+        }
+    }
+}
+
+/// Whether the given value would be read back as the same plain scalar,
+/// following the parser's own rules for where plain scalars start and end
+fn is_plain(value: &str, flow: bool) -> bool {
+    let bytes = value.as_bytes();
+    let safe_at = |i: usize| bytes.get(i).is_some_and(|&c| is_plain_safe(c, flow));
+
+    let (first, last) = match (bytes.first(), bytes.last()) {
+        (Some(first), Some(last)) => (*first, *last),
+        _ => return false,
+    };
+
+    // Leading and trailing whitespace is trimmed, and document markers
+    // are not scalars at all
+    if is_whitespace(&first)
+        || is_whitespace(&last)
+        || value.starts_with("---")
+        || value.starts_with("...")
+    {
+        return false;
+    }
+
+    if is_indicator(first) && !(matches!(first, b'-' | b'?' | b':') && safe_at(1)) {
+        return false;
+    }
+
+    // Flow collections read a leading ':' as the start of an empty key
+    if flow && first == b':' {
+        return false;
+    }
+
+    bytes.iter().enumerate().all(|(i, &c)| match c {
+        b':' => safe_at(i + 1),
+        b'#' => !is_whitespace(&bytes[i - 1]),
+        b',' | b'[' | b']' | b'{' | b'}' => !flow,
+        c => is_printable(c),
+    })
+}
+
+/// Escapes the bytes of the given tag which it can't hold as they are,
+/// along with any of the given reserved characters
+fn encode_uri(tag: &str, reserved: &[u8]) -> String {
+    let mut encoded = String::with_capacity(tag.len());
+
+    for ch in tag.bytes() {
+        match ch {
+            b'%' => encoded.push_str("%25"),
+            ch if is_uri_char(ch) && !reserved.contains(&ch) => encoded.push(ch as char),
+            ch => encoded.push_str(&format!("%{:02X}", ch)),
+        }
+    }
+
+    encoded
+}
+
+/// Escapes the given value for a double quoted scalar
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for ch in value.chars() {
+        match ch {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\0' => escaped.push_str("\\0"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\u{1B}' => escaped.push_str("\\e"),
+            ch if ch < ' ' || ch == '\u{7F}' => escaped.push_str(&format!("\\x{:02X}", ch as u32)),
+            ch => escaped.push(ch),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Parser, ParserConfig};
+
+    fn emit(nodes: &[Node], config: EmitterConfig) -> Result<String> {
+        let mut emitter = Emitter::with_config(Vec::new(), config);
+
+        for node in nodes {
+            emitter.emit(node)?;
+        }
+
+        Ok(String::from_utf8(emitter.into_inner()).unwrap())
+    }
+
+    fn parse(data: &str) -> Result<Vec<Node<'_>>> {
+        Parser::from_str(data).collect()
+    }
+
+    fn nodes(kinds: Vec<NodeKind>) -> Vec<Node> {
+        kinds
+            .into_iter()
+            .map(|kind| kind.at(Default::default()))
+            .collect()
+    }
+
+    /// Quoting and positions may change, but not the content
+    fn unstyled(nodes: Vec<Node>) -> Vec<Node> {
+        nodes
+            .into_iter()
+            .map(|node| Node {
+                kind: match node.kind {
+                    NodeKind::Scalar { value, .. } => NodeKind::scalar(value, ScalarStyle::Plain),
+                    kind => kind,
+                },
+                mark: Default::default(),
+                ..node
+            })
+            .collect()
+    }
+
+    #[test]
+    fn emit_block() -> Result<()> {
+        let data = "a: 1
+b:
+  c:
+    - x
+    - 'y z'
+  d: {}
+e:
+  - - f
+    - g
+  - h: i
+    j: k
+  - []
+";
+
+        assert_eq!(emit(&parse(data)?, Default::default())?, data);
+
+        Ok(())
+    }
+
+    #[test]
+    fn emit_flow() -> Result<()> {
+        let data = "a: 1\nb:\n  - c\n  - d: [e]\n";
+        let config = EmitterConfig { flow: true };
+
+        assert_eq!(emit(&parse(data)?, config)?, "{a: 1, b: [c, {d: [e]}]}\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn emit_quoted() -> Result<()> {
+        let values = [
+            "",
+            " padded ",
+            "- dash",
+            "-dash",
+            "key: value",
+            "key:value",
+            "trailing:",
+            "a #comment",
+            "a#b",
+            "[flow]",
+            "a, b",
+            "it's",
+            "line\nbreak\t\"quoted\"\u{1}",
+            "---",
+            "négatif",
+        ];
+
+        for flow in [false, true] {
+            let mut kinds = vec![
+                NodeKind::StreamStart,
+                NodeKind::DocumentStart(Default::default()),
+                NodeKind::SequenceStart,
+            ];
+            kinds.extend(
+                values
+                    .iter()
+                    .map(|v| NodeKind::scalar(*v, ScalarStyle::Plain)),
+            );
+            kinds.extend(vec![
+                NodeKind::SequenceEnd,
+                NodeKind::DocumentEnd,
+                NodeKind::StreamEnd,
+            ]);
+
+            let nodes = nodes(kinds);
+            let text = emit(&nodes, EmitterConfig { flow })?;
+
+            assert_eq!(unstyled(parse(&text)?), nodes, "{}", text);
+        }
+
+        let text = emit(&parse("[a, 'b', \"c\"]")?, Default::default())?;
+
+        assert!(text.contains("- a\n- 'b'\n- \"c\""), "{}", text);
+
+        Ok(())
+    }
+
+    #[test]
+    fn emit_roundtrip() -> Result<()> {
+        let fixtures = [
+            include_str!("../testing/data/anchors.yaml"),
+            include_str!("../testing/data/documents.yaml"),
+            include_str!("../testing/data/empty-nodes.yaml"),
+            include_str!("../testing/data/flow.yaml"),
+            include_str!("../testing/data/map-explicit.yaml"),
+            include_str!("../testing/data/map-nested.yaml"),
+            include_str!("../testing/data/scalar-block.yaml"),
+            include_str!("../testing/data/scalar-plain.yaml"),
+            include_str!("../testing/data/scalar-quoted.yaml"),
+            include_str!("../testing/data/sequence-nested.yaml"),
+        ];
+
+        for data in fixtures.iter() {
+            for flow in [false, true] {
+                let text = emit(&parse(data)?, EmitterConfig { flow })?;
+
+                assert_eq!(unstyled(parse(&text)?), unstyled(parse(data)?), "{}", text);
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn emit_anchors() -> Result<()> {
+        let data = "a: &x 1
+b: &s
+  - &y c
+  - *y
+  - &e []
+  - &f
+    - g
+d: *x
+--- &r
+- *r
+";
+
+        assert_eq!(emit(&parse(data)?, Default::default())?, data);
+
+        let config = EmitterConfig { flow: true };
+        let text = emit(&parse(data)?, config)?;
+
+        assert_eq!(
+            text,
+            "{a: &x 1, b: &s [&y c, *y, &e [], &f [g]], d: *x}\n--- &r [*r]\n"
+        );
+        assert_eq!(unstyled(parse(&text)?), unstyled(parse(data)?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn emit_tags() -> Result<()> {
+        let data = "a: !!str 1
+b: !local
+  - ! 2
+  - &c !!int 3
+d: !<tag:example.com,2000:point> {x: 1}
+g: !%21bang%20ed h
+---
+- !!map {}
+...
+%TAG !e! tag:example.com,2000:
+--- !e!point
+x: !e!coord%2C 1
+";
+        let expected = "a: !!str 1
+b: !local
+  - ! 2
+  - &c !!int 3
+d: !<tag:example.com,2000:point>
+  x: 1
+g: !%21bang%20ed h
+---
+- !!map {}
+...
+%TAG !e! tag:example.com,2000:
+--- !e!point
+x: !e!coord%2C 1
+";
+
+        assert_eq!(emit(&parse(data)?, Default::default())?, expected);
+
+        for flow in [false, true] {
+            let text = emit(&parse(data)?, EmitterConfig { flow })?;
+
+            assert_eq!(unstyled(parse(&text)?), unstyled(parse(data)?), "{}", text);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn emit_directives() -> Result<()> {
+        let data = "a\n...\n%YAML 1.2\n%TAG !e! tag:example.com,2000:\n---\nb: c\n--- d\n";
+
+        assert_eq!(emit(&parse(data)?, Default::default())?, data);
+
+        Ok(())
+    }
+
+    #[test]
+    fn emit_comments() -> Result<()> {
+        let config = ParserConfig {
+            comments: true,
+            ..Default::default()
+        };
+        let data = "# head\na: 1 # one\nb:\n  - c # see\n";
+        let nodes = Parser::from_str_with_config(data, config)
+            .map(|node| node.map(Node::into_owned))
+            .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(emit(&nodes, Default::default())?, data);
+
+        Ok(())
+    }
+
+    #[test]
+    fn emit_unbalanced() {
+        let nodes = nodes(vec![
+            NodeKind::StreamStart,
+            NodeKind::DocumentStart(Default::default()),
+            NodeKind::MappingStart,
+            NodeKind::SequenceEnd,
+        ]);
+
+        assert!(emit(&nodes, Default::default()).is_err());
+    }
+}
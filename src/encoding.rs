@@ -0,0 +1,267 @@
+use {
+    super::{
+        error::{Error, ErrorKind, Result},
+        scanner::Byte,
+    },
+    std::{collections::VecDeque, str},
+};
+
+/// The character encodings a YAML stream may use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
+}
+
+impl Encoding {
+    /// Detects a stream's encoding from its first (up to) four bytes, using
+    /// either its byte order mark or the pattern of null bytes an ASCII
+    /// first character leaves. Returns the length of any byte order mark
+    fn detect(head: &[u8]) -> (Self, usize) {
+        match head {
+            [0x00, 0x00, 0xFE, 0xFF, ..] => (Self::Utf32Be, 4),
+            [0x00, 0x00, 0x00, _, ..] => (Self::Utf32Be, 0),
+            [0xFF, 0xFE, 0x00, 0x00, ..] => (Self::Utf32Le, 4),
+            [_, 0x00, 0x00, 0x00, ..] => (Self::Utf32Le, 0),
+            [0xFE, 0xFF, ..] => (Self::Utf16Be, 2),
+            [0x00, _, ..] => (Self::Utf16Be, 0),
+            [0xFF, 0xFE, ..] => (Self::Utf16Le, 2),
+            [_, 0x00, ..] => (Self::Utf16Le, 0),
+            [0xEF, 0xBB, 0xBF, ..] => (Self::Utf8, 3),
+            _ => (Self::Utf8, 0),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            Self::Utf32Le => "UTF-32LE",
+            Self::Utf32Be => "UTF-32BE",
+        }
+    }
+}
+
+/// Transcodes a byte stream in any of the supported encodings into
+/// validated UTF-8, detecting the encoding on the first read
+#[derive(Debug)]
+pub(super) struct Decoder<I> {
+    iter: I,
+    encoding: Option<Encoding>,
+    // Bytes read ahead during detection, not yet decoded
+    head: VecDeque<u8>,
+    // UTF-8 bytes of the last decoded character, not yet yielded
+    out: VecDeque<u8>,
+    // Offset of the next undecoded byte in the raw stream
+    offset: usize,
+    // Length of the stream's byte order mark
+    bom: usize,
+}
+
+impl<I> Decoder<I>
+where
+    I: Iterator<Item = Byte>,
+{
+    pub(super) fn new(iter: I) -> Self {
+        Self {
+            iter,
+            encoding: None,
+            head: VecDeque::with_capacity(4),
+            out: VecDeque::with_capacity(4),
+            offset: 0,
+            bom: 0,
+        }
+    }
+
+    /// The offset of UTF-8 input in the raw stream, which is the length of
+    /// any byte order mark. Input in other encodings doesn't map to the raw
+    /// stream, so returns None, as it does before the encoding is detected
+    pub(super) fn utf8_offset(&self) -> Option<usize> {
+        match self.encoding {
+            Some(Encoding::Utf8) => Some(self.bom),
+            _ => None,
+        }
+    }
+
+    /// Reads the stream's head, detecting the encoding and skipping any byte
+    /// order mark
+    fn detect(&mut self) -> Result<Encoding> {
+        while self.head.len() < 4 {
+            match self.iter.next().transpose()? {
+                Some(byte) => self.head.push_back(byte),
+                None => break,
+            }
+        }
+
+        let (encoding, bom) = Encoding::detect(self.head.make_contiguous());
+        self.head.drain(..bom);
+        self.offset = bom;
+        self.bom = bom;
+        self.encoding = Some(encoding);
+
+        Ok(encoding)
+    }
+
+    /// Reads the next raw byte of the stream
+    fn raw(&mut self) -> Result<Option<u8>> {
+        let byte = match self.head.pop_front() {
+            byte @ Some(_) => byte,
+            None => self.iter.next().transpose()?,
+        };
+
+        if byte.is_some() {
+            self.offset += 1;
+        }
+
+        Ok(byte)
+    }
+
+    /// Reads a code unit of the given width, or none at the end of the stream
+    fn unit(&mut self, width: usize, big_endian: bool) -> Result<Option<u32>> {
+        let start = self.offset;
+        let mut unit = 0;
+
+        for i in 0..width {
+            let byte = match self.raw()? {
+                Some(byte) => u32::from(byte),
+                None if i == 0 => return Ok(None),
+                None => return Err(self.invalid(start, "truncated code unit")),
+            };
+
+            unit = if big_endian {
+                unit << 8 | byte
+            } else {
+                unit | byte << (8 * i)
+            };
+        }
+
+        Ok(Some(unit))
+    }
+
+    /// Decodes the next character into the output buffer, returning false
+    /// at the end of the stream
+    fn decode(&mut self, encoding: Encoding) -> Result<bool> {
+        let start = self.offset;
+
+        let ch = match encoding {
+            Encoding::Utf8 => return self.decode_utf8(),
+            Encoding::Utf16Le | Encoding::Utf16Be => {
+                let big_endian = encoding == Encoding::Utf16Be;
+
+                match self.unit(2, big_endian)? {
+                    Some(high @ 0xD800..=0xDBFF) => match self.unit(2, big_endian)? {
+                        Some(low @ 0xDC00..=0xDFFF) => {
+                            0x10000 + ((high - 0xD800) << 10 | (low - 0xDC00))
+                        }
+                        _ => return Err(self.invalid(start, "unpaired surrogate")),
+                    },
+                    Some(0xDC00..=0xDFFF) => return Err(self.invalid(start, "unpaired surrogate")),
+                    Some(unit) => unit,
+                    None => return Ok(false),
+                }
+            }
+            Encoding::Utf32Le | Encoding::Utf32Be => {
+                match self.unit(4, encoding == Encoding::Utf32Be)? {
+                    Some(unit) => unit,
+                    None => return Ok(false),
+                }
+            }
+        };
+
+        let ch = char::from_u32(ch).ok_or_else(|| self.invalid(start, "invalid code point"))?;
+        self.out
+            .extend(ch.encode_utf8(&mut [0; 4]).as_bytes().iter().copied());
+
+        Ok(true)
+    }
+
+    /// Validates the next UTF-8 sequence, moving it to the output buffer
+    fn decode_utf8(&mut self) -> Result<bool> {
+        let start = self.offset;
+        let mut seq = [0; 4];
+
+        let len = match self.raw()? {
+            Some(byte @ 0x00..=0x7F) => {
+                self.out.push_back(byte);
+                return Ok(true);
+            }
+            Some(byte) => {
+                seq[0] = byte;
+
+                match byte {
+                    0xC2..=0xDF => 2,
+                    0xE0..=0xEF => 3,
+                    0xF0..=0xF4 => 4,
+                    _ => return Err(self.invalid(start, "invalid leading byte")),
+                }
+            }
+            None => return Ok(false),
+        };
+
+        for byte in seq.iter_mut().take(len).skip(1) {
+            *byte = match self.raw()? {
+                Some(next) => next,
+                None => return Err(self.invalid(start, "truncated sequence")),
+            };
+        }
+
+        // Catches overlong encodings, surrogates and out of range code points
+        if str::from_utf8(&seq[..len]).is_err() {
+            return Err(self.invalid(start, "invalid sequence"));
+        }
+        self.out.extend(seq[..len].iter().copied());
+
+        Ok(true)
+    }
+
+    fn invalid(&self, offset: usize, reason: &str) -> Error {
+        let encoding = self.encoding.map_or("", Encoding::name);
+
+        ErrorKind::InvalidEncoding(offset)
+            .with_context(format!("{}: {}", encoding, reason).as_str())
+            .into()
+    }
+}
+
+impl<I> Iterator for Decoder<I>
+where
+    I: Iterator<Item = Byte>,
+{
+    type Item = Result<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(byte) = self.out.pop_front() {
+            return Some(Ok(byte));
+        }
+
+        let encoding = match self.encoding {
+            Some(encoding) => encoding,
+            None => match self.detect() {
+                Ok(encoding) => encoding,
+                Err(e) => return Some(Err(e)),
+            },
+        };
+
+        // Fast path for ASCII, which needs no buffering
+        if encoding == Encoding::Utf8 && self.head.is_empty() {
+            match self.iter.next() {
+                Some(Ok(byte)) if byte.is_ascii() => {
+                    self.offset += 1;
+                    return Some(Ok(byte));
+                }
+                Some(Ok(byte)) => self.head.push_back(byte),
+                other => return other.map(|byte| byte.map_err(Into::into)),
+            }
+        }
+
+        match self.decode(encoding) {
+            Ok(true) => self.out.pop_front().map(Ok),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
@@ -4,6 +4,8 @@ use std::{
     io, result,
 };
 
+use super::{mark::Mark, node::Version};
+
 /// Local Result type def
 pub type Result<T> = result::Result<T, Error>;
 
@@ -20,7 +22,22 @@ impl Error {
             | ErrorKind::ScalarInvalid
             | ErrorKind::SoloCarriageReturn
             | ErrorKind::InvalidEOL
-            | ErrorKind::InvalidEOF => Category::Data,
+            | ErrorKind::InvalidEOF
+            | ErrorKind::InvalidIndent
+            | ErrorKind::InvalidEscape
+            | ErrorKind::InvalidEncoding(_)
+            | ErrorKind::InvalidDirective
+            | ErrorKind::UnsupportedVersion(_)
+            | ErrorKind::UnknownAnchor
+            | ErrorKind::InvalidTag
+            | ErrorKind::DuplicateKey
+            | ErrorKind::InvalidMerge
+            | ErrorKind::AliasLimit(_)
+            | ErrorKind::DepthLimit(_)
+            | ErrorKind::ScalarLimit(_)
+            | ErrorKind::DocumentLimit(_)
+            | ErrorKind::DocumentCountLimit(_)
+            | ErrorKind::Unsupported(_) => Category::Data,
             ErrorKind::IllegalTransition
             | ErrorKind::StateViolation
             | ErrorKind::EOFMapping
@@ -36,12 +53,20 @@ impl Error {
         self.inner.cxt.as_ref()
     }
 
+    /// Where in the stream the error occurred, if known
+    pub fn mark(&self) -> Option<Mark> {
+        self.inner.mark
+    }
+
+    /// Sets where the error occurred, unless already set
+    pub(super) fn at(mut self, mark: Mark) -> Self {
+        self.inner.mark.get_or_insert(mark);
+        self
+    }
+
     /// Helper for checking if this is a repeat error
     pub(super) fn is_repeat(&self) -> bool {
-        match self.inner.err {
-            ErrorKind::RepeatFailure => true,
-            _ => false,
-        }
+        matches!(self.inner.err, ErrorKind::RepeatFailure)
     }
 }
 
@@ -69,6 +94,10 @@ impl Display for Error {
             write!(f, " {}", cxt)?;
         }
 
+        if let Some(mark) = self.inner.mark.as_ref() {
+            write!(f, " at {}", mark)?;
+        }
+
         Ok(())
     }
 }
@@ -81,6 +110,10 @@ impl Debug for Error {
             write!(f, " {}", cxt)?;
         }
 
+        if let Some(mark) = self.inner.mark.as_ref() {
+            write!(f, " at {}", mark)?;
+        }
+
         Ok(())
     }
 }
@@ -89,14 +122,13 @@ impl error::Error for Error {}
 
 impl From<Error> for io::Error {
     fn from(e: Error) -> Self {
-        if let ErrorKind::Io(err) = e.inner.err {
-            err
-        } else {
-            match e.categorize() {
-                Category::Io => unreachable!(),
-                Category::Data => io::Error::new(io::ErrorKind::InvalidData, e),
-                Category::State => io::Error::new(io::ErrorKind::InvalidInput, e),
-            }
+        match e.categorize() {
+            Category::Io => match e.inner.err {
+                ErrorKind::Io(err) => err,
+                _ => io::Error::new(io::ErrorKind::Other, e),
+            },
+            Category::Data => io::Error::new(io::ErrorKind::InvalidData, e),
+            Category::State => io::Error::new(io::ErrorKind::InvalidInput, e),
         }
     }
 }
@@ -105,15 +137,16 @@ impl From<Error> for io::Error {
 pub struct Err {
     err: ErrorKind,
     cxt: Option<Context>,
+    mark: Option<Mark>,
 }
 
 impl Err {
     pub(super) fn new(err: ErrorKind, cxt: Option<Context>) -> Self {
-        Self { err, cxt }
-    }
-
-    pub(super) fn with_context<T: Into<Context>>(self, cxt: T) -> Self {
-        Self::new(self.err, Some(cxt.into()))
+        Self {
+            err,
+            cxt,
+            mark: None,
+        }
     }
 }
 
@@ -147,6 +180,40 @@ pub enum ErrorKind {
     InvalidEOF,
 
     SoloCarriageReturn,
+
+    InvalidIndent,
+
+    InvalidEscape,
+
+    // Holds the offset of the offending bytes in the raw stream
+    InvalidEncoding(usize),
+
+    InvalidDirective,
+
+    // Only an error for unknown major versions, otherwise a warning
+    UnsupportedVersion(Version),
+
+    UnknownAnchor,
+
+    InvalidTag,
+
+    DuplicateKey,
+
+    InvalidMerge,
+
+    // Each holds the limit which was exceeded, see Limits
+    AliasLimit(usize),
+
+    DepthLimit(usize),
+
+    ScalarLimit(usize),
+
+    DocumentLimit(usize),
+
+    DocumentCountLimit(usize),
+
+    // Valid YAML which the parser can't handle (yet)
+    Unsupported(Feature),
 }
 
 impl ErrorKind {
@@ -175,6 +242,71 @@ impl Display for ErrorKind {
             Self::InvalidEOL => write!(f, "Parser encountered an invalid EOL"),
             Self::InvalidEOF => write!(f, "Parser encountered an invalid EOF"),
             Self::SoloCarriageReturn => write!(f, "Parser encountered a solo carriage return"),
+            Self::InvalidIndent => write!(f, "Parser encountered an invalid indentation level"),
+            Self::InvalidEscape => write!(f, "Parser encountered an invalid escape sequence"),
+            Self::InvalidEncoding(offset) => write!(
+                f,
+                "Parser encountered invalidly encoded input at byte {}",
+                offset
+            ),
+            Self::InvalidDirective => write!(f, "Parser encountered an invalid directive"),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "Parser encountered an unsupported YAML version: {}",
+                version
+            ),
+            Self::UnknownAnchor => write!(f, "Parser encountered an alias to an unknown anchor"),
+            Self::InvalidTag => write!(f, "Parser encountered an invalid tag"),
+            Self::DuplicateKey => write!(f, "Parser encountered a duplicate mapping key"),
+            Self::InvalidMerge => write!(f, "Parser encountered an invalid merge key value"),
+            Self::AliasLimit(limit) => write!(
+                f,
+                "Parser exceeded the limit of {} nodes expanded from aliases",
+                limit
+            ),
+            Self::DepthLimit(limit) => write!(
+                f,
+                "Parser exceeded the limit of {} nested collections",
+                limit
+            ),
+            Self::ScalarLimit(limit) => {
+                write!(
+                    f,
+                    "Parser exceeded the limit of {} bytes in a scalar",
+                    limit
+                )
+            }
+            Self::DocumentLimit(limit) => write!(
+                f,
+                "Parser exceeded the limit of {} bytes in a document",
+                limit
+            ),
+            Self::DocumentCountLimit(limit) => {
+                write!(f, "Parser exceeded the limit of {} documents", limit)
+            }
+            Self::Unsupported(feature) => {
+                write!(f, "Parser encountered an unsupported feature: {}", feature)
+            }
+        }
+    }
+}
+
+/// Parts of the YAML spec the parser does not support
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// A mapping of a single pair as an entry of a flow sequence,
+    /// e.g. `[a: b]`
+    FlowPair,
+    /// A flow collection as the implicit key of a block mapping,
+    /// e.g. `[a, b]: c`. Explicit keys (`? [a, b]`) are supported
+    FlowKey,
+}
+
+impl Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::FlowPair => write!(f, "single pair mappings in flow sequences"),
+            Self::FlowKey => write!(f, "flow collections as implicit keys"),
         }
     }
 }
@@ -184,11 +316,12 @@ pub enum Context {
     Generic(Box<str>),
     BadChar(u8),
     ExpectedMultipleChar((Vec<u8>, u8)),
+    ExpectedIndent((u16, u16)),
 }
 
 impl From<&str> for Context {
     fn from(s: &str) -> Self {
-        Context::Generic(Box::from(s.as_ref()))
+        Context::Generic(Box::from(s))
     }
 }
 
@@ -204,29 +337,43 @@ impl<T: AsRef<[u8]>> From<(T, u8)> for Context {
     }
 }
 
+impl From<(u16, u16)> for Context {
+    fn from(indent: (u16, u16)) -> Self {
+        Context::ExpectedIndent(indent)
+    }
+}
+
 impl Display for Context {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Generic(cxt) => write!(f, "{}", cxt),
-            Self::BadChar(ch) => write!(f, "Bad char: '{}'", *ch as char),
+            Self::BadChar(ch) => write!(f, "Bad char: '{}'", ch.escape_ascii()),
             Self::ExpectedMultipleChar((good, bad)) => {
-                if good.len() == 0 {
-                    write!(f, "Bad char: '{}'", *bad as char)
+                if good.is_empty() {
+                    write!(f, "Bad char: '{}'", bad.escape_ascii())
                 } else if good.len() == 1 {
-                    write!(f, "Expected: '{}' got: '{}'", good[0] as char, *bad as char)
+                    write!(
+                        f,
+                        "Expected: '{}' got: '{}'",
+                        good[0].escape_ascii(),
+                        bad.escape_ascii()
+                    )
                 } else {
                     write!(f, "Expected one of: [")?;
                     let len = good.len();
                     for (i, ch) in good.iter().enumerate() {
                         if i == len {
-                            write!(f, "'{}'", *ch as char)?;
+                            write!(f, "'{}'", ch.escape_ascii())?;
                         } else {
-                            write!(f, "'{}', ", *ch as char)?;
+                            write!(f, "'{}', ", ch.escape_ascii())?;
                         }
                     }
-                    write!(f, "] got: {}", *bad as char)
+                    write!(f, "] got: {}", bad.escape_ascii())
                 }
             }
+            Self::ExpectedIndent((expected, got)) => {
+                write!(f, "Expected indentation: {} got: {}", expected, got)
+            }
         }
     }
 }
@@ -1,41 +1,45 @@
-use super::{node::NodeKind, Error, Result};
+use super::{node::Node, Error, Result};
 
 /// Type def for an event
-pub(super) type Event = Option<EventKind>;
+pub(super) type Event<'a> = Option<EventKind<'a>>;
 
 /// Describes the possible events that can occur
 #[derive(Debug)]
-pub(super) enum EventKind {
-    Node(NodeKind),
+pub(super) enum EventKind<'a> {
+    Node(Node<'a>),
     Failure(Error),
+    // A problem the parser could continue past
+    Warning(Error),
     Done,
 }
 
-impl EventKind {
+impl<'a> EventKind<'a> {
     /// Converts an event into the equivalent Option/Result nesting
-    pub(super) fn transpose(self) -> Option<Result<NodeKind>> {
+    pub(super) fn transpose(self) -> Option<Result<Node<'a>>> {
         match self {
             Self::Node(node) => Some(Ok(node)),
             Self::Failure(err) if err.is_repeat() => None,
             Self::Failure(err) => Some(Err(err)),
+            // Warnings are collected by the handle instead
+            Self::Warning(_) => None,
             Self::Done => None,
         }
     }
 }
 
-impl From<NodeKind> for EventKind {
-    fn from(node: NodeKind) -> Self {
+impl<'a> From<Node<'a>> for EventKind<'a> {
+    fn from(node: Node<'a>) -> Self {
         Self::Node(node)
     }
 }
 
-impl From<Error> for EventKind {
+impl From<Error> for EventKind<'_> {
     fn from(err: Error) -> Self {
         Self::Failure(err)
     }
 }
 
-impl From<()> for EventKind {
+impl From<()> for EventKind<'_> {
     fn from(_: ()) -> Self {
         Self::Done
     }
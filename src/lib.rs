@@ -1,42 +1,90 @@
-#![allow(dead_code)]
-
+mod compose;
+mod config;
+#[cfg(feature = "serde")]
+mod de;
+mod document;
+mod emitter;
+mod encoding;
 mod error;
 mod event;
 mod machine;
+mod mark;
 mod node;
+mod parser;
+mod reader;
 mod scanner;
+mod schema;
+mod scratch;
+#[cfg(feature = "serde")]
+mod ser;
 mod states;
+mod value;
 
 use std::io;
 
 use crate::{
-    error::{Error, ErrorKind, Result},
-    event::Event,
+    compose::Expansions,
+    event::{Event, EventKind},
     machine::*,
-    node::NodeKind,
+    reader::Reader,
     scanner::*,
     states::*,
 };
 
+pub use crate::{
+    config::{EmitterConfig, Limits, ParserConfig},
+    document::{Document, Documents},
+    emitter::Emitter,
+    error::{Category, Context, Error, ErrorKind, Feature, Result},
+    mark::Mark,
+    node::{Directives, Node, NodeKind, ScalarStyle, Version},
+    parser::Parser,
+    schema::{Core, Failsafe, Json, Merge, Resolved, Schema, Yaml11},
+    value::{Mapping, Sequence, Tagged, Value, ValueIndex},
+};
+
+#[cfg(feature = "serde")]
+pub use crate::{
+    de::{from_nodes_with, from_reader, from_slice, from_str},
+    ser::{to_string, to_writer},
+};
+
 /// State machine handle, this struct operates the state machine
 /// and exposes an higher level interface
-struct Handle<R> {
-    machine: State<io::Bytes<R>>,
+struct Handle<'a, R> {
+    machine: State<'a, Reader<R>>,
+    // Problems the machine has continued past
+    warnings: Vec<Error>,
+    expansions: Expansions,
+    // Set once an error has been yielded, which nothing follows
+    failed: bool,
 }
 
-impl<R> Handle<R>
+impl<'a, R> Handle<'a, R>
 where
     R: io::Read,
 {
     // Initialize a new parse handle
     fn new(stream: R) -> Self {
+        Self::with_config(stream, Default::default())
+    }
+
+    // Initialize a new parse handle with the given config
+    fn with_config(stream: R, config: ParserConfig) -> Self {
         Self {
-            machine: State::new(stream.bytes()),
+            machine: State::new(Reader::new(stream), config, None),
+            warnings: Vec::new(),
+            expansions: Expansions::new(config.limits.aliases),
+            failed: false,
         }
     }
 
     /// Cycles the state machine, returning the next YAML node
-    fn next_node(&mut self) -> Option<Result<NodeKind>> {
+    fn next_node(&mut self) -> Option<Result<Node<'a>>> {
+        if self.failed {
+            return None;
+        }
+
         // Early returns from this function must ensure that the
         // machine is returned to 'self' before returning from the function
 
@@ -47,20 +95,72 @@ where
             let mut output = None;
             machine = machine.step(&mut output);
 
-            if let Some(event) = output {
-                break event.transpose();
+            match output {
+                Some(EventKind::Warning(warning)) => self.warnings.push(warning),
+                Some(event) => break event.transpose(),
+                None => {}
             }
         };
 
         // returned here
         std::mem::swap(&mut self.machine, &mut machine);
 
-        node
+        let node = match node? {
+            Ok(node) => self.expansions.count(&node).map(|()| node),
+            Err(e) => Err(e),
+        };
+        // Even if the machine itself can't tell it has failed
+        self.failed = node.is_err();
+
+        Some(node)
+    }
+
+    /// Cycles the state machine up to the end of the next document,
+    /// returning its nodes
+    fn next_document(&mut self) -> Option<Result<Vec<Node<'a>>>> {
+        let mut nodes = Vec::new();
+
+        while let Some(node) = self.next_node() {
+            let node = match node {
+                Ok(node) => node,
+                Err(e) => return Some(Err(e)),
+            };
+
+            match node.kind {
+                NodeKind::StreamStart | NodeKind::StreamEnd => {}
+                NodeKind::DocumentEnd => {
+                    nodes.push(node);
+
+                    return Some(Ok(nodes));
+                }
+                _ => nodes.push(node),
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a> Handle<'a, &'a [u8]> {
+    // Initialize a new parse handle over the given slice, which
+    // scalars are borrowed from where possible
+    fn from_slice(slice: &'a [u8]) -> Self {
+        Self::from_slice_with_config(slice, Default::default())
+    }
+
+    // Initialize a new parse handle over the given slice with the given config
+    fn from_slice_with_config(slice: &'a [u8], config: ParserConfig) -> Self {
+        Self {
+            machine: State::new(Reader::new(slice), config, Some(slice)),
+            warnings: Vec::new(),
+            expansions: Expansions::new(config.limits.aliases),
+            failed: false,
+        }
     }
 }
 
-impl<R: io::Read> Iterator for Handle<R> {
-    type Item = std::result::Result<NodeKind, Error>;
+impl<'a, R: io::Read> Iterator for Handle<'a, R> {
+    type Item = std::result::Result<Node<'a>, Error>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.next_node()
@@ -69,60 +169,126 @@ impl<R: io::Read> Iterator for Handle<R> {
 
 /// Contains all legal states of the machine
 /// and controls the legal transitions between them
-enum State<I> {
-    Start(StateMachine<I, Start>),
-    LineStart(StateMachine<I, LineStart, Active>),
-    LineEnd(StateMachine<I, LineEnd>),
+enum State<'a, I> {
+    Start(StateMachine<'a, I, Start>),
+    LineStart(StateMachine<'a, I, LineStart, Active>),
+    LineEnd(StateMachine<'a, I, LineEnd>),
+    DocumentStart(StateMachine<'a, I, DocumentStart, Active>),
+    DocumentEnd(StateMachine<'a, I, DocumentEnd>),
+    StreamEnd(StateMachine<'a, I, StreamEnd>),
 
     // Ambiguous
-    AmbiguousScalar(StateMachine<I, AmbiguousScalar>),
-    AmbiguousColon(StateMachine<I, AmbiguousColon>),
+    AmbiguousScalar(StateMachine<'a, I, AmbiguousScalar>),
+    AmbiguousColon(StateMachine<'a, I, AmbiguousColon>),
 
     // Scalar
-    ScalarLiteral(StateMachine<I, ScalarLiteral>),
+    ScalarLiteral(StateMachine<'a, I, ScalarLiteral>),
+    ScalarQuoted(StateMachine<'a, I, ScalarQuoted>),
+    ScalarBlock(StateMachine<'a, I, ScalarBlock>),
+
+    // Node
+    NodeProperties(StateMachine<'a, I, NodeProperties>),
+    Alias(StateMachine<'a, I, Alias>),
+    EmptyNode(StateMachine<'a, I, EmptyNode>),
 
     // Map
-    MapStart(StateMachine<I, MapStart>),
-    MapVerifyKey(StateMachine<I, MapVerifyKey>),
-    MapWhiteSpace(StateMachine<I, MapWhiteSpace>),
-    MapValue(StateMachine<I, MapValue>),
+    MapStart(StateMachine<'a, I, MapStart>),
+    MapVerifyKey(StateMachine<'a, I, MapVerifyKey>),
+    MapWhiteSpace(StateMachine<'a, I, MapWhiteSpace>),
+    MapValue(StateMachine<'a, I, MapValue>),
+    MapEnd(StateMachine<'a, I, MapEnd>),
+    MapExplicitKey(StateMachine<'a, I, MapExplicitKey, Active>),
+    MapExplicitValue(StateMachine<'a, I, MapExplicitValue, Active>),
+
+    // Sequence
+    SequenceStart(StateMachine<'a, I, SequenceStart>),
+    SequenceEntry(StateMachine<'a, I, SequenceEntry, Active>),
+    SequenceEnd(StateMachine<'a, I, SequenceEnd>),
+
+    // Flow
+    FlowStart(StateMachine<'a, I, FlowStart>),
+    FlowEntry(StateMachine<'a, I, FlowEntry>),
+    FlowScalar(StateMachine<'a, I, FlowScalar>),
+    FlowEnd(StateMachine<'a, I, FlowEnd>),
 
     // Exit
-    Done(StateMachine<I, Done>),
-    Failure(StateMachine<I, Failure>),
+    Done(StateMachine<'a, I, Done>),
+    Failure(StateMachine<'a, I, Failure>),
 
     // Dummy type
     Dummy,
 }
 
-impl<I> State<I>
+impl<'a, I> State<'a, I>
 where
     I: Iterator<Item = io::Result<u8>>,
 {
     /// Initialize new binding
-    fn new(stream: I) -> Self {
-        Self::Start(StateMachine::new(stream))
+    fn new(stream: I, config: ParserConfig, source: Option<&'a [u8]>) -> Self {
+        Self::Start(StateMachine::new(stream, config, source))
     }
 
     /// Moves the machine forward one step.
     /// Each state's driver is passed an event handle
     /// which it can use to return collected output (if any.)
-    fn step(self, output: &mut Event) -> Self {
+    fn step(self, output: &mut Event<'a>) -> Self {
         match self {
             Self::Start(mut st) => match st.drive(output) {
-                Ok(Marker::LineStart) => Self::LineStart(st.into()),
-                Ok(Marker::Done) => Self::Done(st.into()),
+                Ok(Marker::DocumentStart) => Self::DocumentStart(st.into()),
+                Ok(Marker::StreamEnd) => Self::StreamEnd(st.into()),
                 Err(e) => Self::Failure((e, st).into()),
                 _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
             },
             Self::LineStart(mut st) => match st.drive(output) {
                 Ok(Marker::LineEnd) => Self::LineEnd(st.into()),
                 Ok(Marker::AmbiguousScalar) => Self::AmbiguousScalar(st.into()),
-                Ok(Marker::Done) => Self::Done(st.into()),
+                Ok(Marker::ScalarQuoted) => Self::ScalarQuoted(st.into()),
+                Ok(Marker::ScalarBlock) => Self::ScalarBlock(st.into()),
+                Ok(Marker::FlowStart) => Self::FlowStart(st.into()),
+                Ok(Marker::SequenceStart) => Self::SequenceStart(st.into()),
+                Ok(Marker::SequenceEnd) => Self::SequenceEnd(st.into()),
+                Ok(Marker::MapEnd) => Self::MapEnd(st.into()),
+                Ok(Marker::DocumentEnd) => Self::DocumentEnd(st.into()),
+                Ok(Marker::NodeProperties) => Self::NodeProperties(st.into()),
+                Ok(Marker::Alias) => Self::Alias(st.into()),
+                Ok(Marker::MapExplicitKey) => Self::MapExplicitKey(st.into()),
+                Ok(Marker::MapExplicitValue) => Self::MapExplicitValue(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
                 _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
             },
             Self::LineEnd(mut st) => match st.drive(output) {
                 Ok(Marker::LineStart) => Self::LineStart(st.into()),
+                Ok(Marker::SequenceEnd) => Self::SequenceEnd(st.into()),
+                Ok(Marker::MapEnd) => Self::MapEnd(st.into()),
+                Ok(Marker::DocumentEnd) => Self::DocumentEnd(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::DocumentStart(mut st) => match st.drive(output) {
+                Ok(Marker::DocumentStart) => Self::DocumentStart(st),
+                Ok(Marker::AmbiguousScalar) => Self::AmbiguousScalar(st.into()),
+                Ok(Marker::ScalarQuoted) => Self::ScalarQuoted(st.into()),
+                Ok(Marker::ScalarBlock) => Self::ScalarBlock(st.into()),
+                Ok(Marker::FlowStart) => Self::FlowStart(st.into()),
+                Ok(Marker::SequenceStart) => Self::SequenceStart(st.into()),
+                Ok(Marker::DocumentEnd) => Self::DocumentEnd(st.into()),
+                Ok(Marker::StreamEnd) => Self::StreamEnd(st.into()),
+                Ok(Marker::NodeProperties) => Self::NodeProperties(st.into()),
+                Ok(Marker::Alias) => Self::Alias(st.into()),
+                Ok(Marker::MapExplicitKey) => Self::MapExplicitKey(st.into()),
+                Ok(Marker::MapExplicitValue) => Self::MapExplicitValue(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::DocumentEnd(mut st) => match st.drive(output) {
+                Ok(Marker::DocumentStart) => Self::DocumentStart(st.into()),
+                Ok(Marker::StreamEnd) => Self::StreamEnd(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::StreamEnd(mut st) => match st.drive(output) {
                 Ok(Marker::Done) => Self::Done(st.into()),
                 Err(e) => Self::Failure((e, st).into()),
                 _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
@@ -141,8 +307,73 @@ where
                 _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
             },
             // ======================
+            Self::NodeProperties(mut st) => match st.drive(output) {
+                Ok(Marker::LineEnd) => Self::LineEnd(st.into()),
+                Ok(Marker::AmbiguousScalar) => Self::AmbiguousScalar(st.into()),
+                Ok(Marker::ScalarQuoted) => Self::ScalarQuoted(st.into()),
+                Ok(Marker::ScalarBlock) => Self::ScalarBlock(st.into()),
+                Ok(Marker::FlowStart) => Self::FlowStart(st.into()),
+                Ok(Marker::MapValue) => Self::MapValue(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::Alias(mut st) => match st.drive(output) {
+                Ok(Marker::MapStart) => Self::MapStart(st.into()),
+                Ok(Marker::LineEnd) => Self::LineEnd(st.into()),
+                Ok(Marker::SequenceEnd) => Self::SequenceEnd(st.into()),
+                Ok(Marker::MapEnd) => Self::MapEnd(st.into()),
+                Ok(Marker::DocumentEnd) => Self::DocumentEnd(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::EmptyNode(mut st) => match st.drive(output) {
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st),
+                Ok(Marker::AmbiguousScalar) => Self::AmbiguousScalar(st.into()),
+                Ok(Marker::ScalarQuoted) => Self::ScalarQuoted(st.into()),
+                Ok(Marker::ScalarBlock) => Self::ScalarBlock(st.into()),
+                Ok(Marker::FlowStart) => Self::FlowStart(st.into()),
+                Ok(Marker::SequenceStart) => Self::SequenceStart(st.into()),
+                Ok(Marker::SequenceEnd) => Self::SequenceEnd(st.into()),
+                Ok(Marker::MapEnd) => Self::MapEnd(st.into()),
+                Ok(Marker::DocumentEnd) => Self::DocumentEnd(st.into()),
+                Ok(Marker::NodeProperties) => Self::NodeProperties(st.into()),
+                Ok(Marker::Alias) => Self::Alias(st.into()),
+                Ok(Marker::MapExplicitKey) => Self::MapExplicitKey(st.into()),
+                Ok(Marker::MapExplicitValue) => Self::MapExplicitValue(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            // ======================
             Self::ScalarLiteral(mut st) => match st.drive(output) {
-                Ok(Marker::Done) => Self::Done(st.into()),
+                Ok(Marker::LineStart) => Self::LineStart(st.into()),
+                Ok(Marker::LineEnd) => Self::LineEnd(st.into()),
+                Ok(Marker::SequenceEnd) => Self::SequenceEnd(st.into()),
+                Ok(Marker::MapEnd) => Self::MapEnd(st.into()),
+                Ok(Marker::DocumentEnd) => Self::DocumentEnd(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::ScalarQuoted(mut st) => match st.drive(output) {
+                Ok(Marker::MapStart) => Self::MapStart(st.into()),
+                Ok(Marker::FlowEntry) => Self::FlowEntry(st.into()),
+                Ok(Marker::LineEnd) => Self::LineEnd(st.into()),
+                Ok(Marker::SequenceEnd) => Self::SequenceEnd(st.into()),
+                Ok(Marker::MapEnd) => Self::MapEnd(st.into()),
+                Ok(Marker::DocumentEnd) => Self::DocumentEnd(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::ScalarBlock(mut st) => match st.drive(output) {
+                Ok(Marker::ScalarBlock) => Self::ScalarBlock(st),
+                Ok(Marker::LineStart) => Self::LineStart(st.into()),
+                Ok(Marker::SequenceEnd) => Self::SequenceEnd(st.into()),
+                Ok(Marker::MapEnd) => Self::MapEnd(st.into()),
+                Ok(Marker::DocumentEnd) => Self::DocumentEnd(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
                 Err(e) => Self::Failure((e, st).into()),
                 _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
             },
@@ -159,12 +390,136 @@ where
             },
             Self::MapWhiteSpace(mut st) => match st.drive(output) {
                 Ok(Marker::MapValue) => Self::MapValue(st.into()),
+                Ok(Marker::ScalarQuoted) => Self::ScalarQuoted(st.into()),
+                Ok(Marker::ScalarBlock) => Self::ScalarBlock(st.into()),
+                Ok(Marker::FlowStart) => Self::FlowStart(st.into()),
+                Ok(Marker::LineEnd) => Self::LineEnd(st.into()),
+                Ok(Marker::NodeProperties) => Self::NodeProperties(st.into()),
+                Ok(Marker::Alias) => Self::Alias(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
                 Err(e) => Self::Failure((e, st).into()),
                 _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
             },
             Self::MapValue(mut st) => match st.drive(output) {
+                Ok(Marker::LineStart) => Self::LineStart(st.into()),
                 Ok(Marker::LineEnd) => Self::LineEnd(st.into()),
-                Ok(Marker::Done) => Self::Done(st.into()),
+                Ok(Marker::SequenceEnd) => Self::SequenceEnd(st.into()),
+                Ok(Marker::MapEnd) => Self::MapEnd(st.into()),
+                Ok(Marker::DocumentEnd) => Self::DocumentEnd(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::MapEnd(mut st) => match st.drive(output) {
+                Ok(Marker::MapEnd) => Self::MapEnd(st),
+                Ok(Marker::SequenceEnd) => Self::SequenceEnd(st.into()),
+                Ok(Marker::AmbiguousScalar) => Self::AmbiguousScalar(st.into()),
+                Ok(Marker::ScalarQuoted) => Self::ScalarQuoted(st.into()),
+                Ok(Marker::ScalarBlock) => Self::ScalarBlock(st.into()),
+                Ok(Marker::FlowStart) => Self::FlowStart(st.into()),
+                Ok(Marker::SequenceStart) => Self::SequenceStart(st.into()),
+                Ok(Marker::DocumentEnd) => Self::DocumentEnd(st.into()),
+                Ok(Marker::NodeProperties) => Self::NodeProperties(st.into()),
+                Ok(Marker::Alias) => Self::Alias(st.into()),
+                Ok(Marker::MapExplicitKey) => Self::MapExplicitKey(st.into()),
+                Ok(Marker::MapExplicitValue) => Self::MapExplicitValue(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::MapExplicitKey(mut st) => match st.drive(output) {
+                Ok(Marker::MapExplicitKey) => Self::MapExplicitKey(st),
+                Ok(Marker::LineEnd) => Self::LineEnd(st.into()),
+                Ok(Marker::AmbiguousScalar) => Self::AmbiguousScalar(st.into()),
+                Ok(Marker::ScalarQuoted) => Self::ScalarQuoted(st.into()),
+                Ok(Marker::ScalarBlock) => Self::ScalarBlock(st.into()),
+                Ok(Marker::FlowStart) => Self::FlowStart(st.into()),
+                Ok(Marker::SequenceStart) => Self::SequenceStart(st.into()),
+                Ok(Marker::NodeProperties) => Self::NodeProperties(st.into()),
+                Ok(Marker::Alias) => Self::Alias(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::MapExplicitValue(mut st) => match st.drive(output) {
+                Ok(Marker::MapExplicitKey) => Self::MapExplicitKey(st.into()),
+                Ok(Marker::LineEnd) => Self::LineEnd(st.into()),
+                Ok(Marker::AmbiguousScalar) => Self::AmbiguousScalar(st.into()),
+                Ok(Marker::ScalarQuoted) => Self::ScalarQuoted(st.into()),
+                Ok(Marker::ScalarBlock) => Self::ScalarBlock(st.into()),
+                Ok(Marker::FlowStart) => Self::FlowStart(st.into()),
+                Ok(Marker::SequenceStart) => Self::SequenceStart(st.into()),
+                Ok(Marker::NodeProperties) => Self::NodeProperties(st.into()),
+                Ok(Marker::Alias) => Self::Alias(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            // ======================
+            Self::SequenceStart(mut st) => match st.drive(output) {
+                Ok(Marker::SequenceEntry) => Self::SequenceEntry(st.into()),
+                Ok(Marker::AmbiguousScalar) => Self::AmbiguousScalar(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::SequenceEntry(mut st) => match st.drive(output) {
+                Ok(Marker::LineEnd) => Self::LineEnd(st.into()),
+                Ok(Marker::AmbiguousScalar) => Self::AmbiguousScalar(st.into()),
+                Ok(Marker::ScalarQuoted) => Self::ScalarQuoted(st.into()),
+                Ok(Marker::ScalarBlock) => Self::ScalarBlock(st.into()),
+                Ok(Marker::FlowStart) => Self::FlowStart(st.into()),
+                Ok(Marker::SequenceStart) => Self::SequenceStart(st.into()),
+                Ok(Marker::NodeProperties) => Self::NodeProperties(st.into()),
+                Ok(Marker::Alias) => Self::Alias(st.into()),
+                Ok(Marker::MapExplicitKey) => Self::MapExplicitKey(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::SequenceEnd(mut st) => match st.drive(output) {
+                Ok(Marker::SequenceEnd) => Self::SequenceEnd(st),
+                Ok(Marker::MapEnd) => Self::MapEnd(st.into()),
+                Ok(Marker::AmbiguousScalar) => Self::AmbiguousScalar(st.into()),
+                Ok(Marker::ScalarQuoted) => Self::ScalarQuoted(st.into()),
+                Ok(Marker::ScalarBlock) => Self::ScalarBlock(st.into()),
+                Ok(Marker::FlowStart) => Self::FlowStart(st.into()),
+                Ok(Marker::SequenceStart) => Self::SequenceStart(st.into()),
+                Ok(Marker::DocumentEnd) => Self::DocumentEnd(st.into()),
+                Ok(Marker::NodeProperties) => Self::NodeProperties(st.into()),
+                Ok(Marker::Alias) => Self::Alias(st.into()),
+                Ok(Marker::MapExplicitKey) => Self::MapExplicitKey(st.into()),
+                Ok(Marker::MapExplicitValue) => Self::MapExplicitValue(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            // ======================
+            Self::FlowStart(mut st) => match st.drive(output) {
+                Ok(Marker::FlowEntry) => Self::FlowEntry(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::FlowEntry(mut st) => match st.drive(output) {
+                Ok(Marker::FlowEntry) => Self::FlowEntry(st),
+                Ok(Marker::FlowStart) => Self::FlowStart(st.into()),
+                Ok(Marker::FlowScalar) => Self::FlowScalar(st.into()),
+                Ok(Marker::FlowEnd) => Self::FlowEnd(st.into()),
+                Ok(Marker::ScalarQuoted) => Self::ScalarQuoted(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::FlowScalar(mut st) => match st.drive(output) {
+                Ok(Marker::FlowEntry) => Self::FlowEntry(st.into()),
+                Err(e) => Self::Failure((e, st).into()),
+                _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
+            },
+            Self::FlowEnd(mut st) => match st.drive(output) {
+                Ok(Marker::FlowEntry) => Self::FlowEntry(st.into()),
+                Ok(Marker::LineEnd) => Self::LineEnd(st.into()),
+                Ok(Marker::SequenceEnd) => Self::SequenceEnd(st.into()),
+                Ok(Marker::MapEnd) => Self::MapEnd(st.into()),
+                Ok(Marker::DocumentEnd) => Self::DocumentEnd(st.into()),
+                Ok(Marker::EmptyNode) => Self::EmptyNode(st.into()),
                 Err(e) => Self::Failure((e, st).into()),
                 _ => Self::Failure((ErrorKind::IllegalTransition.into(), st).into()),
             },
@@ -173,7 +528,14 @@ where
             Self::Failure(st) => Self::Failure(st.cycle(output)),
 
             // ======================
-            Self::Dummy => panic!("Attempted to use a dummy state... this is a bug"),
+            // Only left behind if a previous step unwound, the handle
+            // stops at the error
+            Self::Dummy => {
+                let err = ErrorKind::StateViolation.with_context("the parser's state was lost");
+                *output = Some(Error::from(err).into());
+
+                Self::Dummy
+            }
         }
     }
 }
@@ -181,100 +543,1493 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::{io::Cursor, str::from_utf8};
+    use std::io::Cursor;
+
+    /// Wraps the given nodes in the events of a single document stream
+    fn document(nodes: Vec<NodeKind>) -> Vec<NodeKind> {
+        let mut stream = vec![
+            NodeKind::StreamStart,
+            NodeKind::DocumentStart(Default::default()),
+        ];
+        stream.extend(nodes);
+        stream.extend(vec![NodeKind::DocumentEnd, NodeKind::StreamEnd]);
+
+        stream
+    }
+
+    fn plain(value: &str) -> NodeKind<'_> {
+        NodeKind::scalar(value, ScalarStyle::Plain)
+    }
 
     #[test]
-    fn key_plain() -> Result<()> {
-        let data = Cursor::new(include_str!("../testing/data/key-plain.yaml"));
-        let handle = Handle::new(data);
+    fn parser_from_str() -> Result<()> {
+        let nodes = Parser::from_str("key: value").collect::<Result<Vec<_>>>()?;
 
-        handle
-            .into_iter()
-            .take(100)
-            .try_for_each(|node: Result<NodeKind>| {
-                match node? {
-                    NodeKind::Key(v) | NodeKind::ScalarPlain(v) => {
-                        println!("Value: {:?}", from_utf8(&v))
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("key"),
+                plain("value"),
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_empty() -> Result<()> {
+        let nodes = Parser::from_str("\n\n").collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(nodes, vec![NodeKind::StreamStart, NodeKind::StreamEnd]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_chunked() -> Result<()> {
+        /// Yields a byte per read, interrupting every other read
+        struct Trickle<'a>(&'a [u8], bool);
+
+        impl io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.1 = !self.1;
+
+                match (self.1, self.0.split_first()) {
+                    (true, _) => Err(io::ErrorKind::Interrupted.into()),
+                    (false, Some((&byte, rest))) => {
+                        buf[0] = byte;
+                        self.0 = rest;
+                        Ok(1)
                     }
+                    (false, None) => Ok(0),
                 }
+            }
+        }
 
-                Ok(())
-            })
+        let value = "é".repeat(5000);
+        let data = format!("key: {}\n", value);
+        let nodes = Handle::new(Trickle(data.as_bytes(), false))
+            .take(100)
+            .collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("key"),
+                plain(&value),
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        Ok(())
     }
 
     #[test]
-    fn key_squote() -> Result<()> {
-        let data = Cursor::new(include_str!("../testing/data/key-squote.yaml"));
-        let handle = Handle::new(data);
+    fn stream_borrowed() -> Result<()> {
+        use std::borrow::Cow;
 
-        handle
-            .into_iter()
+        let data = "key: value\n'quoted key': [a b]\nescaped: \"a\\tb\"\nfolded: a\n  b\npadded: 'it''s  '\n";
+        let bom = [&[0xEF, 0xBB, 0xBF], data.as_bytes()].concat();
+
+        let config = ParserConfig {
+            comments: true,
+            ..Default::default()
+        };
+        let parsers = [
+            Parser::from_str(data),
+            Parser::from_slice(&bom),
+            Parser::from_str_with_config(data, config),
+        ];
+
+        for parser in parsers {
+            let scalars = parser
+                .filter_map(|node| match node {
+                    Ok(Node {
+                        kind: NodeKind::Scalar { value, .. },
+                        ..
+                    }) => Some(Ok(value)),
+                    Ok(_) => None,
+                    Err(e) => Some(Err(e)),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let borrowed = scalars
+                .iter()
+                .map(|value| matches!(value, Cow::Borrowed(_)))
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                scalars,
+                vec![
+                    "key",
+                    "value",
+                    "quoted key",
+                    "a b",
+                    "escaped",
+                    "a\tb",
+                    "folded",
+                    "a b",
+                    "padded",
+                    "it's  "
+                ]
+            );
+            assert_eq!(
+                borrowed,
+                vec![true, true, true, true, true, false, true, false, true, false]
+            );
+        }
+
+        // Readers can't be borrowed from
+        let owned = Handle::new(Cursor::new(data))
             .take(100)
-            .try_for_each(|node: Result<NodeKind>| {
-                match node? {
-                    NodeKind::Key(v) | NodeKind::ScalarPlain(v) => {
-                        println!("Value: {:?}", from_utf8(&v))
+            .collect::<Result<Vec<_>>>()?
+            .into_iter()
+            .all(|node| {
+                !matches!(
+                    node.kind,
+                    NodeKind::Scalar {
+                        value: Cow::Borrowed(_),
+                        ..
                     }
-                }
+                )
+            });
 
-                Ok(())
-            })
+        assert!(owned);
+
+        Ok(())
+    }
+
+    #[test]
+    fn key_plain() -> Result<()> {
+        let data = Cursor::new(include_str!("../testing/data/key-plain.yaml"));
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("key"),
+                plain("value one:testing"),
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn key_squote() -> Result<()> {
+        let data = Cursor::new(include_str!("../testing/data/key-squote.yaml"));
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("key"),
+                NodeKind::scalar("value one:testing", ScalarStyle::SingleQuoted),
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        Ok(())
     }
 
     #[test]
     fn key_dquote() -> Result<()> {
         let data = Cursor::new(include_str!("../testing/data/key-dquote.yaml"));
-        let handle = Handle::new(data);
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
 
-        handle
-            .into_iter()
-            .take(100)
-            .try_for_each(|node: Result<NodeKind>| {
-                match node? {
-                    NodeKind::Key(v) | NodeKind::ScalarPlain(v) => {
-                        println!("Value: {:?}", from_utf8(&v))
-                    }
-                }
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("key"),
+                NodeKind::scalar("value one:testing", ScalarStyle::DoubleQuoted),
+                NodeKind::MappingEnd,
+            ])
+        );
 
-                Ok(())
-            })
+        Ok(())
+    }
+
+    #[test]
+    fn scalar_quoted() -> Result<()> {
+        let data = Cursor::new(include_str!("../testing/data/scalar-quoted.yaml"));
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
+        let single = |s: &'static str| NodeKind::scalar(s, ScalarStyle::SingleQuoted);
+        let double = |s: &'static str| NodeKind::scalar(s, ScalarStyle::DoubleQuoted);
+
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                single("single key"),
+                single("it's"),
+                double("double key"),
+                double("a\tbA\u{e9}\u{1F600}\\\""),
+                plain("folded"),
+                single("one two\nthree"),
+                plain("escaped"),
+                double("one two"),
+                plain("seq"),
+                NodeKind::SequenceStart,
+                NodeKind::MappingStart,
+                double("item"),
+                single("value"),
+                NodeKind::MappingEnd,
+                NodeKind::SequenceEnd,
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn scalar_quoted_invalid_escape() {
+        let err = Parser::from_str(r#""\q""#)
+            .collect::<Result<Vec<_>>>()
+            .unwrap_err();
+
+        assert!(matches!(err.kind(), ErrorKind::InvalidEscape));
+    }
+
+    #[test]
+    fn scalar_block() -> Result<()> {
+        let data = Cursor::new(include_str!("../testing/data/scalar-block.yaml"));
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
+        let literal = |s: &'static str| NodeKind::scalar(s, ScalarStyle::Literal);
+        let folded = |s: &'static str| NodeKind::scalar(s, ScalarStyle::Folded);
+
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("literal"),
+                literal("first line\n  indented\nlast line\n"),
+                plain("folded"),
+                folded("some folded text\nnew paragraph\n  kept as is\nend\n"),
+                plain("strip"),
+                literal("text"),
+                plain("keep"),
+                literal("text\n\n"),
+                plain("clip"),
+                folded("text\n"),
+                plain("indicator"),
+                literal("  leading spaces\ndone\n"),
+                plain("list"),
+                NodeKind::SequenceStart,
+                literal("entry\n"),
+                folded("folded entry continued"),
+                NodeKind::SequenceEnd,
+                plain("tail"),
+                plain("value"),
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn scalar_block_folding() -> Result<()> {
+        let data = ">\n\n folded\n line\n\n next\n line\n   * bullet\n\n   * list\n   * lines\n\n last\n line\n";
+        let nodes = Parser::from_str(data).collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            nodes,
+            document(vec![NodeKind::scalar(
+                "\nfolded line\nnext line\n  * bullet\n\n  * list\n  * lines\n\nlast line\n",
+                ScalarStyle::Folded
+            )])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn scalar_plain() -> Result<()> {
+        let data = Cursor::new(include_str!("../testing/data/scalar-plain.yaml"));
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("version"),
+                plain("1.2.3"),
+                plain("path"),
+                plain("/usr/bin"),
+                plain("url"),
+                plain("http://example.com:8080/a#b"),
+                plain("key with spaces"),
+                plain("value with spaces"),
+                plain("négatif"),
+                plain("-42"),
+                plain("odd"),
+                plain("a:b, [c] {d}"),
+                plain("multi"),
+                plain("this is folded over\nlines"),
+                plain("list"),
+                NodeKind::SequenceStart,
+                plain("one two"),
+                plain("-x"),
+                plain(":y"),
+                NodeKind::SequenceEnd,
+                plain("flow"),
+                NodeKind::SequenceStart,
+                plain("a b"),
+                plain("c:d"),
+                plain("http://x"),
+                plain("multi line"),
+                NodeKind::SequenceEnd,
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn scalar_plain_multiline() -> Result<()> {
+        let nodes = Parser::from_str("a\n b\n\n  c # comment\n").collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(nodes, document(vec![plain("a b\nc")]));
+
+        Ok(())
+    }
+
+    #[test]
+    fn scalar_plain_invalid() {
+        for data in &["a: b: c", "a: - b", "a\n  b: c", "a: @b", "a: b\x07"] {
+            let err = Parser::from_str(data)
+                .collect::<Result<Vec<_>>>()
+                .unwrap_err();
+
+            assert!(matches!(err.kind(), ErrorKind::InvalidChar), "{}", data);
+        }
+    }
+
+    #[test]
+    fn encoding_detected() -> Result<()> {
+        let text = "kéy: välue 🦀\n";
+        let utf16 = text.encode_utf16();
+        let utf32 = text.chars().map(u32::from);
+        let inputs: Vec<Vec<u8>> = vec![
+            text.bytes().collect(),
+            [&[0xEF, 0xBB, 0xBF], text.as_bytes()].concat(),
+            utf16.clone().flat_map(u16::to_le_bytes).collect(),
+            utf16.clone().flat_map(u16::to_be_bytes).collect(),
+            [0xFF, 0xFE]
+                .iter()
+                .copied()
+                .chain(utf16.flat_map(u16::to_le_bytes))
+                .collect(),
+            utf32.clone().flat_map(u32::to_le_bytes).collect(),
+            [0, 0, 0xFE, 0xFF]
+                .iter()
+                .copied()
+                .chain(utf32.flat_map(u32::to_be_bytes))
+                .collect(),
+        ];
+
+        for input in inputs {
+            let nodes = Handle::new(Cursor::new(input))
+                .take(100)
+                .collect::<Result<Vec<_>>>()?;
+
+            assert_eq!(
+                nodes,
+                document(vec![
+                    NodeKind::MappingStart,
+                    plain("kéy"),
+                    plain("välue 🦀"),
+                    NodeKind::MappingEnd,
+                ])
+            );
+            assert_eq!(nodes[4].mark.column, 5);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn encoding_invalid() {
+        let inputs: Vec<(&[u8], usize)> = vec![
+            (b"a: \xFF\n", 3),
+            (b"a: b\xC3", 4),
+            (b"a: \xE0\x80\x80", 3),
+            (&[b'a', 0, b':', 0, b' ', 0, 0x00, 0xDC], 6),
+        ];
+
+        for (input, offset) in inputs {
+            let err = Handle::new(Cursor::new(input))
+                .take(100)
+                .collect::<Result<Vec<_>>>()
+                .unwrap_err();
+
+            assert!(matches!(err.kind(), ErrorKind::InvalidEncoding(o) if *o == offset));
+        }
     }
 
     #[test]
     fn map_plain() -> Result<()> {
         let data = Cursor::new(include_str!("../testing/data/map-plain.yaml"));
-        let handle = Handle::new(data);
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
 
-        handle
-            .into_iter()
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("map"),
+                NodeKind::MappingStart,
+                plain("key"),
+                plain("value"),
+                plain("foo"),
+                plain("bar"),
+                NodeKind::MappingEnd,
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn map_nested() -> Result<()> {
+        let data = Cursor::new(include_str!("../testing/data/map-nested.yaml"));
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("a"),
+                NodeKind::MappingStart,
+                plain("b"),
+                plain("c"),
+                plain("d"),
+                NodeKind::MappingStart,
+                plain("e"),
+                plain("f"),
+                NodeKind::MappingEnd,
+                NodeKind::MappingEnd,
+                plain("g"),
+                NodeKind::SequenceStart,
+                NodeKind::MappingStart,
+                plain("h"),
+                plain("i"),
+                plain("j"),
+                plain("k"),
+                NodeKind::MappingEnd,
+                NodeKind::SequenceEnd,
+                plain("l"),
+                plain("m"),
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn map_explicit() -> Result<()> {
+        let data = Cursor::new(include_str!("../testing/data/map-explicit.yaml"));
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("simple"),
+                plain("value"),
+                plain("a multi line key"),
+                NodeKind::SequenceStart,
+                plain("with"),
+                plain("sequence"),
+                NodeKind::SequenceEnd,
+                NodeKind::SequenceStart,
+                plain("sequence"),
+                plain("key"),
+                NodeKind::SequenceEnd,
+                NodeKind::MappingStart,
+                plain("mapping"),
+                plain("value"),
+                plain("other"),
+                plain("entry"),
+                NodeKind::MappingEnd,
+                NodeKind::MappingStart,
+                plain("nested"),
+                plain("mapping"),
+                NodeKind::MappingEnd,
+                NodeKind::MappingStart,
+                plain("explicit"),
+                plain("again"),
+                NodeKind::MappingEnd,
+                plain("implicit"),
+                plain("too"),
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        // Explicit entries can be compact in sequences, or given in flow
+        let nodes = Parser::from_str("- ? a\n  : b\n- {? c : d}\n").collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::SequenceStart,
+                NodeKind::MappingStart,
+                plain("a"),
+                plain("b"),
+                NodeKind::MappingEnd,
+                NodeKind::MappingStart,
+                plain("c"),
+                plain("d"),
+                NodeKind::MappingEnd,
+                NodeKind::SequenceEnd,
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn map_explicit_invalid() {
+        let invalid = ["? a\n  : b\n", ": a\n", "a: b\n: c\n"];
+
+        for data in invalid.iter() {
+            let err = Parser::from_str(data)
+                .collect::<Result<Vec<_>>>()
+                .unwrap_err();
+
+            assert!(matches!(err.kind(), ErrorKind::InvalidChar), "{}", data);
+        }
+    }
+
+    #[test]
+    fn empty_nodes() -> Result<()> {
+        let data = Cursor::new(include_str!("../testing/data/empty-nodes.yaml"));
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("empty"),
+                plain(""),
+                plain("before"),
+                NodeKind::MappingStart,
+                plain("dedent"),
+                plain(""),
+                NodeKind::MappingEnd,
+                plain("sequence"),
+                NodeKind::SequenceStart,
+                plain(""),
+                plain(""),
+                plain(""),
+                plain("entry"),
+                NodeKind::SequenceEnd,
+                plain("explicit"),
+                NodeKind::MappingStart,
+                plain("key"),
+                plain(""),
+                plain(""),
+                plain("value"),
+                NodeKind::MappingEnd,
+                plain("last"),
+                plain(""),
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        // The only tag is the empty entry's, just before 'entry'
+        let tagged = nodes.windows(2).find(|pair| pair[1] == plain("entry"));
+        assert_eq!(
+            tagged.and_then(|pair| pair[0].tag.as_deref()),
+            Some("tag:yaml.org,2002:str")
+        );
+        assert_eq!(nodes.iter().filter(|node| node.tag.is_some()).count(), 1);
+
+        // Missing nodes at the end of the stream are empty as well
+        let data = [
+            (
+                "a:",
+                vec![
+                    NodeKind::MappingStart,
+                    plain("a"),
+                    plain(""),
+                    NodeKind::MappingEnd,
+                ],
+            ),
+            (
+                "- a\n-",
+                vec![
+                    NodeKind::SequenceStart,
+                    plain("a"),
+                    plain(""),
+                    NodeKind::SequenceEnd,
+                ],
+            ),
+            (
+                "? a",
+                vec![
+                    NodeKind::MappingStart,
+                    plain("a"),
+                    plain(""),
+                    NodeKind::MappingEnd,
+                ],
+            ),
+            ("--- !e", vec![plain("")]),
+        ];
+
+        for (data, expected) in data {
+            let nodes = Parser::from_str(data).collect::<Result<Vec<_>>>()?;
+
+            assert_eq!(nodes, document(expected), "{}", data);
+        }
+
+        // Properties of a root scalar don't leave an empty node behind
+        let data = [
+            ("&a 3", Some("a"), None),
+            ("!custom 3", None, Some("!custom")),
+            ("!!str 3\n", None, Some("tag:yaml.org,2002:str")),
+            ("--- &a !c 3", Some("a"), Some("!c")),
+            ("&a '3'", Some("a"), None),
+        ];
+
+        for (data, anchor, tag) in data {
+            let nodes = Parser::from_str(data).collect::<Result<Vec<_>>>()?;
+
+            assert_eq!(nodes.len(), 5, "{}", data);
+            assert!(matches!(&nodes[2].kind, NodeKind::Scalar { value, .. } if value == "3"));
+            assert_eq!(nodes[2].anchor.as_deref(), anchor, "{}", data);
+            assert_eq!(nodes[2].tag.as_deref(), tag, "{}", data);
+        }
+
+        // Nor do those ending a flow sequence, which stand on its last entry
+        let data = [("[!b]", None, Some("!b")), ("[&a]", Some("a"), None)];
+
+        for (data, anchor, tag) in data {
+            let nodes = Parser::from_str(data).collect::<Result<Vec<_>>>()?;
+            let expected = vec![NodeKind::SequenceStart, plain(""), NodeKind::SequenceEnd];
+            let empty = nodes.iter().find(|node| **node == plain(""));
+
+            assert_eq!(nodes, document(expected), "{}", data);
+            assert_eq!(empty.and_then(|node| node.anchor.as_deref()), anchor);
+            assert_eq!(empty.and_then(|node| node.tag.as_deref()), tag);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_nodes_invalid() {
+        let err = Parser::from_str("[a, , b]")
+            .collect::<Result<Vec<_>>>()
+            .unwrap_err();
+
+        assert!(matches!(err.kind(), ErrorKind::InvalidChar));
+    }
+
+    #[test]
+    fn map_invalid_dedent() {
+        let data = Cursor::new("a:\n    b: c\n  d: e\n");
+        let err = Handle::new(data)
             .take(100)
-            .try_for_each(|node: Result<NodeKind>| {
-                match node? {
-                    NodeKind::Key(v) | NodeKind::ScalarPlain(v) => {
-                        println!("Value: {:?}", from_utf8(&v))
-                    }
-                }
+            .collect::<Result<Vec<_>>>()
+            .unwrap_err();
+
+        assert!(matches!(err.kind(), ErrorKind::InvalidIndent));
+    }
+
+    #[test]
+    fn map_invalid_key() {
+        let invalid = [
+            "a: b\nc\nd: e\n",
+            "a:\n  b: c\n  d\n",
+            "a: b\n'c'\n",
+            "a: &x b\n*x\n",
+            "a: b\n|\n  c\n",
+            "k: v\n!x\n",
+            "k: v\n&x\n",
+        ];
+
+        for data in invalid.iter() {
+            let err = Parser::from_str(data)
+                .collect::<Result<Vec<_>>>()
+                .unwrap_err();
+
+            assert!(matches!(err.kind(), ErrorKind::InvalidChar), "{}", data);
+        }
+    }
+
+    #[test]
+    fn map_invalid_tabs() -> Result<()> {
+        for data in ["\tk: v\n", "a:\n\tb: c\n", "a:\n  b: c\n \td: e\n"].iter() {
+            let err = Parser::from_str(data)
+                .collect::<Result<Vec<_>>>()
+                .unwrap_err();
+
+            assert!(matches!(err.kind(), ErrorKind::InvalidIndent), "{}", data);
+        }
+
+        // Tabs may still separate a node from its indicator, or fill blank lines
+        let nodes =
+            Parser::from_str("a:\tb\n\t\n  \t# c\nc:\n-\td\n").collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("a"),
+                plain("b"),
+                plain("c"),
+                NodeKind::SequenceStart,
+                plain("d"),
+                NodeKind::SequenceEnd,
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn flow() -> Result<()> {
+        let data = Cursor::new(include_str!("../testing/data/flow.yaml"));
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
+        let single = |s: &'static str| NodeKind::scalar(s, ScalarStyle::SingleQuoted);
+        let double = |s: &'static str| NodeKind::scalar(s, ScalarStyle::DoubleQuoted);
+
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("sequence"),
+                NodeKind::SequenceStart,
+                plain("one"),
+                plain("two"),
+                plain("three"),
+                NodeKind::SequenceEnd,
+                plain("mapping"),
+                NodeKind::MappingStart,
+                plain("a"),
+                plain("b"),
+                plain("c"),
+                plain("d"),
+                NodeKind::MappingEnd,
+                plain("nested"),
+                NodeKind::SequenceStart,
+                plain("one"),
+                NodeKind::SequenceStart,
+                plain("two"),
+                plain("three"),
+                NodeKind::SequenceEnd,
+                NodeKind::MappingStart,
+                plain("four"),
+                plain("five"),
+                NodeKind::MappingEnd,
+                NodeKind::SequenceEnd,
+                plain("quoted"),
+                NodeKind::MappingStart,
+                double("json"),
+                double("like"),
+                single("single"),
+                NodeKind::SequenceStart,
+                plain("x"),
+                NodeKind::SequenceEnd,
+                NodeKind::MappingEnd,
+                plain("multiline"),
+                NodeKind::SequenceStart,
+                plain("one"),
+                plain("two"),
+                NodeKind::SequenceEnd,
+                plain("empty"),
+                NodeKind::MappingStart,
+                plain("a"),
+                plain(""),
+                plain("b"),
+                plain(""),
+                plain("c"),
+                plain("d"),
+                NodeKind::MappingEnd,
+                plain("list"),
+                NodeKind::SequenceStart,
+                NodeKind::SequenceStart,
+                plain("a"),
+                plain("b"),
+                NodeKind::SequenceEnd,
+                NodeKind::MappingStart,
+                NodeKind::MappingEnd,
+                NodeKind::SequenceEnd,
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn flow_mismatched_bracket() {
+        let err = Parser::from_str("[a, {b: c]]")
+            .collect::<Result<Vec<_>>>()
+            .unwrap_err();
+
+        assert!(matches!(err.kind(), ErrorKind::InvalidChar));
+    }
+
+    #[test]
+    fn flow_unsupported() {
+        let unsupported = [
+            ("[a: b]", Feature::FlowPair),
+            ("[\"a\": b]", Feature::FlowPair),
+            ("[a, b]: c", Feature::FlowKey),
+            ("- {a: b}: c", Feature::FlowKey),
+        ];
+
+        for (data, feature) in unsupported.iter() {
+            let err = Parser::from_str(data)
+                .collect::<Result<Vec<_>>>()
+                .unwrap_err();
 
-                Ok(())
+            assert!(
+                matches!(err.kind(), ErrorKind::Unsupported(f) if f == feature),
+                "{}",
+                data
+            );
+        }
+    }
+
+    #[test]
+    fn comments_skipped() -> Result<()> {
+        let data = Cursor::new(include_str!("../testing/data/comments.yaml"));
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("key"),
+                plain("value"),
+                plain("other"),
+                plain("a#b"),
+                plain("list"),
+                NodeKind::SequenceStart,
+                plain("one"),
+                NodeKind::scalar("two", ScalarStyle::DoubleQuoted),
+                NodeKind::SequenceStart,
+                plain("x"),
+                plain("y"),
+                NodeKind::SequenceEnd,
+                NodeKind::SequenceEnd,
+                plain("block"),
+                NodeKind::scalar("text # not a comment\n", ScalarStyle::Literal),
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn comments_emitted() -> Result<()> {
+        let data = Cursor::new(include_str!("../testing/data/comments.yaml"));
+        let config = ParserConfig {
+            comments: true,
+            ..Default::default()
+        };
+        let nodes = Handle::with_config(data, config)
+            .take(100)
+            .collect::<Result<Vec<_>>>()?;
+        let comment = |text: &'static str| NodeKind::Comment(text.into());
+
+        let comments = nodes
+            .iter()
+            .filter_map(|node| match &node.kind {
+                NodeKind::Comment(text) => Some((text.as_ref(), node.mark.line)),
+                _ => None,
             })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            comments,
+            vec![
+                (" leading comment", 0),
+                (" trailing", 2),
+                (" between", 4),
+                (" after key", 5),
+                (" entry", 6),
+                (" quoted", 7),
+                (" inside flow", 8),
+                (" header", 10),
+                (" final", 12),
+            ]
+        );
+
+        // Comments are emitted in stream order, around the nodes they follow
+        assert_eq!(nodes[1], comment(" leading comment"));
+        assert_eq!(nodes[6], comment(" trailing"));
+        assert_eq!(nodes[6].mark.column, 11);
+
+        Ok(())
+    }
+
+    #[test]
+    fn node_marks() -> Result<()> {
+        let nodes = Parser::from_str("a:\n  - b\n  - 'c'\n").collect::<Result<Vec<_>>>()?;
+        let marks = nodes
+            .iter()
+            .map(|node| (node.mark.line, node.mark.column, node.mark.index))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            marks,
+            vec![
+                (0, 0, 0),  // StreamStart
+                (0, 0, 0),  // DocumentStart
+                (0, 0, 0),  // MappingStart
+                (0, 0, 0),  // a
+                (1, 2, 5),  // SequenceStart
+                (1, 4, 7),  // b
+                (2, 4, 13), // 'c'
+                (3, 0, 17), // SequenceEnd
+                (3, 0, 17), // MappingEnd
+                (3, 0, 17), // DocumentEnd
+                (3, 0, 17), // StreamEnd
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn error_mark() {
+        let err = Parser::from_str("a: b\nc: 'd\n")
+            .collect::<Result<Vec<_>>>()
+            .unwrap_err();
+        let mark = err.mark().expect("errors are marked");
+
+        assert_eq!((mark.line, mark.column, mark.index), (2, 0, 11));
+        assert!(err.to_string().ends_with("at line 3 column 1"));
     }
 
     #[test]
     fn sequence_plain() -> Result<()> {
         let data = Cursor::new(include_str!("../testing/data/sequence-plain.yaml"));
-        let handle = Handle::new(data);
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
 
-        handle
-            .into_iter()
-            .take(100)
-            .try_for_each(|node: Result<NodeKind>| {
-                match node? {
-                    NodeKind::Key(v) | NodeKind::ScalarPlain(v) => {
-                        println!("Value: {:?}", from_utf8(&v))
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("sequence"),
+                NodeKind::SequenceStart,
+                plain("one"),
+                plain("two"),
+                plain("three"),
+                NodeKind::SequenceEnd,
+                NodeKind::MappingEnd,
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn sequence_nested() -> Result<()> {
+        let data = Cursor::new(include_str!("../testing/data/sequence-nested.yaml"));
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
+
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::SequenceStart,
+                plain("one"),
+                NodeKind::SequenceStart,
+                plain("two"),
+                plain("three"),
+                NodeKind::SequenceEnd,
+                NodeKind::SequenceStart,
+                plain("four"),
+                NodeKind::SequenceEnd,
+                NodeKind::SequenceEnd,
+            ])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_documents() -> Result<()> {
+        let data = Cursor::new(include_str!("../testing/data/documents.yaml"));
+        let nodes = Handle::new(data).take(100).collect::<Result<Vec<_>>>()?;
+        let (start, end) = (
+            NodeKind::DocumentStart(Default::default()),
+            NodeKind::DocumentEnd,
+        );
+
+        assert_eq!(
+            nodes,
+            vec![
+                NodeKind::StreamStart,
+                start.clone(),
+                NodeKind::MappingStart,
+                plain("apiVersion"),
+                plain("v1"),
+                plain("kind"),
+                plain("Namespace"),
+                NodeKind::MappingEnd,
+                end.clone(),
+                start.clone(),
+                NodeKind::MappingStart,
+                plain("apiVersion"),
+                plain("apps/v1"),
+                plain("kind"),
+                plain("Deployment"),
+                plain("spec"),
+                NodeKind::MappingStart,
+                plain("replicas"),
+                plain("2"),
+                NodeKind::MappingEnd,
+                NodeKind::MappingEnd,
+                end.clone(),
+                start.clone(),
+                NodeKind::scalar("literal\n", ScalarStyle::Literal),
+                end.clone(),
+                start.clone(),
+                plain(""),
+                end.clone(),
+                start,
+                NodeKind::SequenceStart,
+                plain("a"),
+                plain("b"),
+                NodeKind::SequenceEnd,
+                end,
+                NodeKind::StreamEnd,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_document_markers() -> Result<()> {
+        // Markers must start a line and be followed by whitespace
+        let nodes = Parser::from_str("---a\n ---\n").collect::<Result<Vec<_>>>()?;
+        assert_eq!(nodes, document(vec![plain("---a ---")]));
+
+        let nodes = Parser::from_str("a\n...\n...\n").collect::<Result<Vec<_>>>()?;
+        assert_eq!(nodes, document(vec![plain("a")]));
+
+        let err = Parser::from_str("a\n... b\n")
+            .collect::<Result<Vec<_>>>()
+            .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidChar));
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_multiple_roots() -> Result<()> {
+        let invalid = [
+            "- a\n- b\nc\n",
+            "- a\nb: c\n",
+            "a: b\n- c\n",
+            "'a'\nb: c\n",
+            "*a\nb\n",
+            "[a]\nb\n",
+            "|\n  a\nb\n",
+        ];
+
+        for data in invalid.iter() {
+            let err = Parser::from_str(data)
+                .collect::<Result<Vec<_>>>()
+                .unwrap_err();
+
+            assert!(matches!(err.kind(), ErrorKind::InvalidChar), "{}", data);
+        }
+
+        // Each document has its own root
+        let nodes = Parser::from_str("- a\n---\nb: c\n").collect::<Result<Vec<_>>>()?;
+        assert_eq!(nodes.len(), 13);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_directives() -> Result<()> {
+        let data = "%YAML 1.2\n%TAG !e! tag:example.com,2000: # c\n---\na\n...\n%YAML 1.3\n%FOO bar\n--- b\n";
+        let mut parser = Parser::from_str(data);
+        let nodes = parser.by_ref().collect::<Result<Vec<_>>>()?;
+        let directives = Directives {
+            version: Some(Version { major: 1, minor: 2 }),
+            tags: vec![("!e!".into(), "tag:example.com,2000:".into())],
+        };
+
+        assert_eq!(nodes[1], NodeKind::DocumentStart(directives.clone()));
+        assert_eq!(nodes[1].mark.line, 2);
+        assert_eq!(directives.prefix("!e!"), Some("tag:example.com,2000:"));
+        assert_eq!(directives.prefix("!!"), Some("tag:yaml.org,2002:"));
+        assert_eq!(directives.prefix("!x!"), None);
+
+        // Directives only apply to the document they precede
+        let version = Some(Version { major: 1, minor: 3 });
+        assert_eq!(
+            nodes[4],
+            NodeKind::DocumentStart(Directives {
+                version,
+                ..Default::default()
+            })
+        );
+
+        let warnings = parser
+            .warnings()
+            .iter()
+            .map(|warning| (warning.kind(), warning.mark().map(|mark| mark.line)))
+            .collect::<Vec<_>>();
+        assert!(matches!(
+            warnings[..],
+            [
+                (ErrorKind::UnsupportedVersion(_), Some(5)),
+                (ErrorKind::InvalidDirective, Some(6))
+            ]
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_anchors() -> Result<()> {
+        let data = "&a a: &b\n  - *a\n  - &c [*b, &d {*c : x}]\n*c: &e 'y'\n";
+        let nodes = Parser::from_str(data).collect::<Result<Vec<_>>>()?;
+        let anchors = nodes
+            .iter()
+            .filter_map(|node| node.anchor.as_deref().map(|anchor| (anchor, &node.kind)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            nodes,
+            document(vec![
+                NodeKind::MappingStart,
+                plain("a"),
+                NodeKind::SequenceStart,
+                NodeKind::Alias("a".into()),
+                NodeKind::SequenceStart,
+                NodeKind::Alias("b".into()),
+                NodeKind::MappingStart,
+                NodeKind::Alias("c".into()),
+                plain("x"),
+                NodeKind::MappingEnd,
+                NodeKind::SequenceEnd,
+                NodeKind::SequenceEnd,
+                NodeKind::Alias("c".into()),
+                NodeKind::scalar("y", ScalarStyle::SingleQuoted),
+                NodeKind::MappingEnd,
+            ])
+        );
+        assert_eq!(
+            anchors,
+            [
+                ("a", &plain("a")),
+                ("b", &NodeKind::SequenceStart),
+                ("c", &NodeKind::SequenceStart),
+                ("d", &NodeKind::MappingStart),
+                ("e", &NodeKind::scalar("y", ScalarStyle::SingleQuoted)),
+            ]
+        );
+
+        // Properties on a line of their own belong to the collection below
+        let nodes = Parser::from_str("&m\nk: v\n").collect::<Result<Vec<_>>>()?;
+        assert_eq!(nodes[2], NodeKind::MappingStart);
+        assert_eq!(nodes[2].anchor.as_deref(), Some("m"));
+        assert_eq!(nodes[3].anchor, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_anchors_invalid() {
+        let invalid = ["&a &b c\n", "- &\n", "- &a *b\n", "[&a *b]\n", "a: *b c\n"];
+
+        for data in invalid.iter() {
+            let err = Parser::from_str(data)
+                .collect::<Result<Vec<_>>>()
+                .unwrap_err();
+
+            assert_eq!(err.categorize(), Category::Data, "{}", data);
+        }
+    }
+
+    #[test]
+    fn stream_tags() -> Result<()> {
+        let data = "%TAG !e! tag:example.com,2000:app/
+---
+a: !!str 1
+b: !e!point%21 &p {x: !local 1, y: ! 2}
+!<tag:example.com,2000:key> c: !!seq
+  - [!!int 3, !e!n 4]
+";
+        let nodes = Parser::from_str(data).collect::<Result<Vec<_>>>()?;
+        let tags = nodes
+            .iter()
+            .filter_map(|node| node.tag.as_deref().map(|tag| (tag, &node.kind)))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            tags,
+            [
+                ("tag:yaml.org,2002:str", &plain("1")),
+                ("tag:example.com,2000:app/point!", &NodeKind::MappingStart),
+                ("!local", &plain("1")),
+                ("!", &plain("2")),
+                ("tag:example.com,2000:key", &plain("c")),
+                ("tag:yaml.org,2002:seq", &NodeKind::SequenceStart),
+                ("tag:yaml.org,2002:int", &plain("3")),
+                ("tag:example.com,2000:app/n", &plain("4")),
+            ]
+        );
+        assert_eq!(nodes[6].anchor.as_deref(), Some("p"));
+
+        // Properties on a line of their own belong to the collection below
+        let nodes = Parser::from_str("--- !!map\nk: v\n").collect::<Result<Vec<_>>>()?;
+        assert_eq!(nodes[2], NodeKind::MappingStart);
+        assert_eq!(nodes[2].tag.as_deref(), Some("tag:yaml.org,2002:map"));
+
+        // Properties with no node after them in a flow collection belong
+        // to an empty one
+        let nodes = Parser::from_str("[!!str , b]").collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            nodes[2..6],
+            [
+                NodeKind::SequenceStart,
+                plain(""),
+                plain("b"),
+                NodeKind::SequenceEnd
+            ]
+        );
+        assert_eq!(nodes[3].tag.as_deref(), Some("tag:yaml.org,2002:str"));
+
+        let nodes = Parser::from_str("[!t]").collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            nodes[2..5],
+            [NodeKind::SequenceStart, plain(""), NodeKind::SequenceEnd]
+        );
+        assert_eq!(nodes[3].tag.as_deref(), Some("!t"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn stream_tags_invalid() {
+        let invalid = [
+            "!x!y a\n",
+            "!! a\n",
+            "!<> a\n",
+            "!a !b c\n",
+            "- !a%zz b\n",
+            "a: !e!\n",
+        ];
+
+        for data in invalid.iter() {
+            let err = Parser::from_str(data)
+                .collect::<Result<Vec<_>>>()
+                .unwrap_err();
+
+            assert!(matches!(err.kind(), ErrorKind::InvalidTag), "{}", data);
+            assert_eq!(err.categorize(), Category::Data);
+        }
+    }
+
+    #[test]
+    fn stream_limits() {
+        let config = ParserConfig {
+            limits: Limits {
+                aliases: 10,
+                depth: 3,
+                scalar: 8,
+                document: 64,
+                documents: 2,
+            },
+            ..Default::default()
+        };
+        let parse = |data: &str| {
+            Parser::from_str_with_config(data, config)
+                .collect::<Result<Vec<_>>>()
+                .map(drop)
+        };
+
+        let exceeded = [
+            (
+                "a: &a [x, x, x]\nb: [*a, *a, *a]\n",
+                ErrorKind::AliasLimit(10),
+            ),
+            ("[[[[a]]]]\n", ErrorKind::DepthLimit(3)),
+            ("a:\n  b:\n    - c: d\n", ErrorKind::DepthLimit(3)),
+            ("a: 'bbbbbbbbb'\n", ErrorKind::ScalarLimit(8)),
+            (&"- a\n".repeat(20), ErrorKind::DocumentLimit(64)),
+            ("a\n--- b\n--- c\n", ErrorKind::DocumentCountLimit(2)),
+        ];
+
+        for (data, kind) in exceeded.iter() {
+            let err = parse(data).unwrap_err();
+
+            assert_eq!(err.kind().to_string(), kind.to_string(), "{}", data);
+            assert_eq!(err.categorize(), Category::Data);
+        }
+
+        // Limits on documents' bytes and aliases apply to each document alone
+        let data = format!("{}---\n{}", "- a\n".repeat(15), "- a\n".repeat(15));
+        assert!(parse(&data).is_ok());
+        assert!(parse("a: &a [x, x]\nb: *a\n--- [&b x, *b, *b]\n").is_ok());
+
+        // Nothing follows the error
+        let mut parser = Parser::from_str_with_config("[&a [x, x, x], *a, *a, *a]", config);
+        assert!(parser.by_ref().any(|node| node.is_err()));
+        assert!(parser.next().is_none());
+    }
+
+    #[test]
+    fn stream_scalar_limit() {
+        let config = ParserConfig {
+            limits: Limits {
+                scalar: 8,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let content = "x".repeat(1 << 20);
+        let lines = "x\n".repeat(1 << 18);
+        let styles = [
+            format!("a: {}\n", content),
+            format!("a: '{}'\n", content),
+            format!("a: \"{}\"\n", "\\t".repeat(1 << 18)),
+            format!("a: |\n  {}\n", content),
+            format!("a: >\n{}", lines.replace('x', "  x")),
+            format!("a: x\n{}", lines.replace('x', "  x")),
+        ];
+
+        // The scalar is cut off as soon as it exceeds the limit
+        for data in styles.iter() {
+            let err = Parser::from_str_with_config(data, config)
+                .collect::<Result<Vec<_>>>()
+                .unwrap_err();
+
+            assert!(matches!(err.kind(), ErrorKind::ScalarLimit(8)), "{}", err);
+            assert!(
+                err.mark().is_some_and(|mark| mark.index < 64),
+                "{:?}",
+                err.mark()
+            );
+        }
+    }
+
+    #[test]
+    fn stream_billion_laughs() {
+        let mut data = String::from("a0: &a0 [x, x, x, x, x, x, x, x, x, x]\n");
+
+        for i in 1..8 {
+            let aliases = vec![format!("*a{}", i - 1); 10].join(", ");
+            data.push_str(&format!("a{}: &a{} [{}]\n", i, i, aliases));
+        }
+
+        let err = Parser::from_str(&data)
+            .collect::<Result<Vec<_>>>()
+            .unwrap_err();
+
+        assert!(matches!(err.kind(), ErrorKind::AliasLimit(_)));
+    }
+
+    #[test]
+    fn stream_directives_invalid() {
+        let invalid = [
+            "%YAML 2.0\n---\n",
+            "%YAML 1.2\n%YAML 1.2\n---\n",
+            "%YAML 1.x\n---\n",
+            "%YAML 1.2\na\n",
+            "%TAG !e !e-\n---\n",
+            "%TAG ! !a\n%TAG ! !b\n---\n",
+        ];
+
+        for data in invalid.iter() {
+            let err = Parser::from_str(data)
+                .collect::<Result<Vec<_>>>()
+                .unwrap_err();
+
+            assert_eq!(err.categorize(), Category::Data, "{}", data);
+        }
+    }
+
+    #[test]
+    fn handle_lost_state() {
+        let mut handle = Handle::from_slice(b"a: b\n");
+        assert!(matches!(handle.next(), Some(Ok(_))));
+
+        // As if a step had unwound, leaving no state behind
+        handle.machine = State::Dummy;
+
+        let err = handle.next().unwrap().unwrap_err();
+        assert_eq!(err.categorize(), Category::State);
+        assert!(handle.next().is_none());
+        assert!(handle.next().is_none());
+    }
+
+    #[test]
+    fn handle_never_panics() {
+        // Bytes which steer the parser into its less travelled states
+        const ALPHABET: &[u8] = b" \t\n\r-?:,[]{}#&*!|>'\"%@`.~\\0aZ\xef\xbb\xbf\x00\xff";
+
+        let fixtures = [
+            include_str!("../testing/data/anchors.yaml"),
+            include_str!("../testing/data/documents.yaml"),
+            include_str!("../testing/data/empty-nodes.yaml"),
+            include_str!("../testing/data/flow.yaml"),
+            include_str!("../testing/data/map-explicit.yaml"),
+            include_str!("../testing/data/scalar-block.yaml"),
+            include_str!("../testing/data/scalar-quoted.yaml"),
+        ];
+        // Xorshift, seeded so any failure is reproducible
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as usize
+        };
+
+        for round in 0..4000 {
+            let data: Vec<u8> = match round % 3 {
+                // Arbitrary bytes
+                0 => (0..next() % 64).map(|_| next() as u8).collect(),
+                // Bytes of YAML's syntax
+                1 => (0..next() % 64)
+                    .map(|_| ALPHABET[next() % ALPHABET.len()])
+                    .collect(),
+                // A valid document with a few bytes replaced
+                _ => {
+                    let mut data = fixtures[next() % fixtures.len()].as_bytes().to_vec();
+
+                    for _ in 0..1 + next() % 4 {
+                        let at = next() % data.len();
+                        data[at] = ALPHABET[next() % ALPHABET.len()];
                     }
+
+                    data
                 }
+            };
 
-                Ok(())
-            })
+            let parsed = std::panic::catch_unwind(|| {
+                Handle::new(Cursor::new(&data)).take(1000).for_each(drop);
+                Handle::from_slice(&data).take(1000).for_each(drop);
+            });
+
+            assert!(
+                parsed.is_ok(),
+                "panicked on {:?}",
+                String::from_utf8_lossy(&data)
+            );
+        }
     }
 }
@@ -1,50 +1,49 @@
+use std::mem::take;
+
 use super::{
+    config::ParserConfig,
     error::{Error, Result},
-    event::Event,
-    node::NodeKind,
+    event::{Event, EventKind},
+    node::{NodeKind, ScalarStyle},
     scanner::*,
     states::*,
 };
 
-pub(super) struct StateMachine<I, S = Start, INDENT = Inactive> {
+pub(super) struct StateMachine<'a, I, S = Start, INDENT = Inactive> {
     // Current state
     state: S,
 
     // Byte iter
-    scan: Scan<I, INDENT>,
+    scan: Scan<'a, I, INDENT>,
 }
 
-impl<I> StateMachine<I>
+impl<'a, I> StateMachine<'a, I>
 where
     I: Iterator<Item = Byte>,
 {
-    pub(super) fn new(stream: I) -> Self {
+    pub(super) fn new(stream: I, config: ParserConfig, source: Option<&'a [u8]>) -> Self {
         StateMachine {
             state: Default::default(),
-            scan: Scan::new(stream),
+            scan: Scan::new(stream, config, source),
         }
     }
 }
 
-impl<I> StateMachine<I, Done>
+impl<'a, I> StateMachine<'a, I, Done>
 where
     I: Iterator<Item = Byte>,
 {
-    pub(super) fn done(&self) -> String {
-        format!("Finished! Indent history: {:?}", self.scan.history())
-    }
-
-    pub(super) fn cycle(self, output: &mut Event) -> Self {
+    pub(super) fn cycle(self, output: &mut Event<'a>) -> Self {
         *output = Some(().into());
         self
     }
 }
 
-impl<I> StateMachine<I, Failure>
+impl<'a, I> StateMachine<'a, I, Failure>
 where
     I: Iterator<Item = Byte>,
 {
-    pub(super) fn cycle(mut self, output: &mut Event) -> Self {
+    pub(super) fn cycle(mut self, output: &mut Event<'a>) -> Self {
         *output = Some(self.state.error().into());
         self
     }
@@ -58,22 +57,89 @@ pub(super) trait Drive {
     fn drive(&mut self, _: &mut Self::Event) -> Result<Marker>;
 }
 
-impl<I> Drive for StateMachine<I>
+impl<'a, I> Drive for StateMachine<'a, I>
 where
     I: Iterator<Item = Byte>,
 {
-    type Event = Event;
+    type Event = Event<'a>;
+
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        *output = Some(NodeKind::StreamStart.at(self.scan.mark()).into());
+
+        self.state.find_next(&mut self.scan)
+    }
+}
+
+impl<'a, I> Drive for StateMachine<'a, I, DocumentStart, Active>
+where
+    I: Iterator<Item = Byte>,
+{
+    type Event = Event<'a>;
+
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        // An explicit document without a node holds an empty scalar
+        if take(&mut self.state.empty) {
+            let node = NodeKind::scalar("", ScalarStyle::Plain);
+            let mark = self.scan.mark();
+            *output = Some(self.scan.node(node, mark).into());
+
+            return Ok(Marker::DocumentEnd);
+        }
+
+        let content = self.state.skip_blank(&mut self.scan)?;
+        let marker = self.state.find_next(&mut self.scan)?;
+
+        if content {
+            let directives = take(&mut self.state.directives);
+            self.scan.start_document(directives.clone())?;
+
+            let mark = self.state.mark.unwrap_or_else(|| self.scan.mark());
+            let node = NodeKind::DocumentStart(directives);
+            *output = Some(node.at(mark).into());
+        } else if let Some(comment) = take(&mut self.state.comment) {
+            *output = Some(comment.into());
+        } else if let Some(warning) = take(&mut self.state.warning) {
+            *output = Some(EventKind::Warning(warning));
+        }
+
+        Ok(marker)
+    }
+}
+
+impl<'a, I> Drive for StateMachine<'a, I, DocumentEnd>
+where
+    I: Iterator<Item = Byte>,
+{
+    type Event = Event<'a>;
+
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        *output = Some(NodeKind::DocumentEnd.at(self.scan.mark()).into());
+
+        self.state.close(&mut self.scan)?;
+        self.scan.end_document();
 
-    fn drive(&mut self, _: &mut Self::Event) -> Result<Marker> {
         self.state.find_next(&mut self.scan)
     }
 }
 
-impl<I> Drive for StateMachine<I, LineStart, Active>
+impl<'a, I> Drive for StateMachine<'a, I, StreamEnd>
 where
     I: Iterator<Item = Byte>,
 {
-    type Event = Event;
+    type Event = Event<'a>;
+
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        *output = Some(NodeKind::StreamEnd.at(self.scan.mark()).into());
+
+        self.state.find_next(&mut self.scan)
+    }
+}
+
+impl<'a, I> Drive for StateMachine<'a, I, LineStart, Active>
+where
+    I: Iterator<Item = Byte>,
+{
+    type Event = Event<'a>;
 
     fn drive(&mut self, _: &mut Self::Event) -> Result<Marker> {
         self.state
@@ -82,311 +148,438 @@ where
     }
 }
 
-impl<I> Drive for StateMachine<I, LineEnd>
+impl<'a, I> Drive for StateMachine<'a, I, LineEnd>
+where
+    I: Iterator<Item = Byte>,
+{
+    type Event = Event<'a>;
+
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        self.state.close_line(&mut self.scan)?;
+
+        if let Some(comment) = take(&mut self.state.comment) {
+            *output = Some(comment.into());
+        }
+
+        self.state.find_next(&mut self.scan)
+    }
+}
+
+/* Node Drivers */
+impl<'a, I> Drive for StateMachine<'a, I, NodeProperties>
 where
     I: Iterator<Item = Byte>,
 {
-    type Event = Event;
+    type Event = Event<'a>;
 
     fn drive(&mut self, _: &mut Self::Event) -> Result<Marker> {
         self.state
-            .close_line(&mut self.scan)
+            .parse_properties(&mut self.scan)
             .and(self.state.find_next(&mut self.scan))
     }
 }
 
-/* Ambiguous Drivers */
-impl<I> Drive for StateMachine<I, AmbiguousScalar>
+impl<'a, I> Drive for StateMachine<'a, I, Alias>
 where
     I: Iterator<Item = Byte>,
 {
-    type Event = Event;
+    type Event = Event<'a>;
 
-    fn drive(&mut self, _: &mut Self::Event) -> Result<Marker> {
-        self.state.find_next(&mut self.scan)
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        self.state.parse_alias(&mut self.scan)?;
+        // A key marks its mapping pending again once read
+        self.scan.set_pending(false);
+        // The properties are the node's own, not those of an empty node past it
+        let properties = self.scan.take_properties();
+        let marker = self.state.find_next(&mut self.scan)?;
+        self.scan.set_properties(properties);
+
+        // Keys are emitted once their mapping is (potentially) opened
+        if marker != Marker::MapStart {
+            let name = self.scan.resolve(take(&mut self.state.name))?;
+            let node = NodeKind::Alias(name);
+            *output = Some(self.scan.node(node, self.state.mark).into());
+        }
+
+        Ok(marker)
     }
 }
 
-impl<I> Drive for StateMachine<I, AmbiguousColon>
+impl<'a, I> Drive for StateMachine<'a, I, EmptyNode>
 where
     I: Iterator<Item = Byte>,
 {
-    type Event = Event;
+    type Event = Event<'a>;
+
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        let node = NodeKind::scalar("", ScalarStyle::Plain);
+        let mark = self.scan.mark();
+        *output = Some(self.scan.node(node, mark).into());
+        self.state.resolve(&mut self.scan);
 
-    fn drive(&mut self, _: &mut Self::Event) -> Result<Marker> {
         self.state.find_next(&mut self.scan)
     }
 }
 
-/* Scalar Drivers */
-impl<I> Drive for StateMachine<I, ScalarLiteral>
+/* Ambiguous Drivers */
+impl<'a, I> Drive for StateMachine<'a, I, AmbiguousScalar>
 where
     I: Iterator<Item = Byte>,
 {
-    type Event = Event;
+    type Event = Event<'a>;
 
-    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
-        self.state.find_next(&mut self.scan).and_then(|m| {
-            *output = Some(NodeKind::ScalarPlain(take(&mut self.state.scalar)).into());
-            Ok(m)
-        })
+    fn drive(&mut self, _: &mut Self::Event) -> Result<Marker> {
+        self.state.find_next(&mut self.scan)
     }
 }
 
-/* Map Drivers */
-impl<I> Drive for StateMachine<I, MapStart>
+impl<'a, I> Drive for StateMachine<'a, I, AmbiguousColon>
 where
     I: Iterator<Item = Byte>,
 {
-    type Event = Event;
+    type Event = Event<'a>;
 
     fn drive(&mut self, _: &mut Self::Event) -> Result<Marker> {
         self.state.find_next(&mut self.scan)
     }
 }
 
-impl<I> Drive for StateMachine<I, MapVerifyKey>
+/* Scalar Drivers */
+impl<'a, I> Drive for StateMachine<'a, I, ScalarLiteral>
 where
     I: Iterator<Item = Byte>,
 {
-    type Event = Event;
+    type Event = Event<'a>;
 
     fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
-        self.state
-            .parse_key(&mut self.scan)
-            .and_then(|v| {
-                *output = Some(NodeKind::Key(take(&mut self.state.key)).into());
-                Ok(v)
-            })
-            .and(self.state.find_next(&mut self.scan))
+        self.state.parse_scalar(&mut self.scan)?;
+        self.scan.set_pending(false);
+        // The properties are the node's own, not those of an empty node past it
+        let properties = self.scan.take_properties();
+        let marker = self.state.find_next(&mut self.scan)?;
+        self.scan.set_properties(properties);
+
+        let scalar = self.scan.resolve(take(&mut self.state.scalar))?;
+        let node = NodeKind::scalar(scalar, ScalarStyle::Plain);
+        *output = Some(self.scan.node(node, self.state.mark).into());
+
+        Ok(marker)
     }
 }
 
-impl<I> Drive for StateMachine<I, MapWhiteSpace>
+impl<'a, I> Drive for StateMachine<'a, I, ScalarQuoted>
 where
     I: Iterator<Item = Byte>,
 {
-    type Event = Event;
+    type Event = Event<'a>;
 
-    fn drive(&mut self, _: &mut Self::Event) -> Result<Marker> {
-        self.state
-            .parse_whitespace(&mut self.scan)
-            .and(self.state.find_next(&mut self.scan))
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        self.state.parse_scalar(&mut self.scan)?;
+        // A key marks its mapping pending again once read
+        self.scan.set_pending(false);
+        // The properties are the node's own, not those of an empty node past it
+        let properties = self.scan.take_properties();
+        let marker = self.state.find_next(&mut self.scan)?;
+        self.scan.set_properties(properties);
+
+        // Keys are emitted once their mapping is (potentially) opened
+        if marker != Marker::MapStart {
+            let scalar = self.scan.resolve(take(&mut self.state.scalar))?;
+            let node = NodeKind::scalar(scalar, self.state.style());
+            *output = Some(self.scan.node(node, self.state.mark).into());
+        }
+
+        Ok(marker)
     }
 }
 
-impl<I> Drive for StateMachine<I, MapValue>
+impl<'a, I> Drive for StateMachine<'a, I, ScalarBlock>
 where
     I: Iterator<Item = Byte>,
 {
-    type Event = Event;
+    type Event = Event<'a>;
 
     fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
-        self.state
-            .parse_value(&mut self.scan)
-            .and_then(|v| {
-                *output = Some(NodeKind::ScalarPlain(take(&mut self.state.value)).into());
-                Ok(v)
-            })
-            .and(self.state.find_next(&mut self.scan))
+        self.state.parse_header(&mut self.scan)?;
+
+        // A comment in the header is emitted before the scalar
+        if let Some(comment) = take(&mut self.state.comment) {
+            *output = Some(comment.into());
+
+            return Ok(Marker::ScalarBlock);
+        }
+
+        self.state.parse_content(&mut self.scan)?;
+
+        let scalar = self.scan.resolve(take(&mut self.state.scalar))?;
+        let node = NodeKind::scalar(scalar, self.state.style());
+        *output = Some(self.scan.node(node, self.state.mark).into());
+        self.scan.set_pending(false);
+
+        self.state.find_next(&mut self.scan)
     }
 }
 
-/* Legal state transitions */
-/* ======================= */
-impl<I> From<StateMachine<I, Start>> for StateMachine<I, LineStart, Active>
+/* Map Drivers */
+impl<'a, I> Drive for StateMachine<'a, I, MapStart>
 where
     I: Iterator<Item = Byte>,
 {
-    fn from(prev: StateMachine<I, Start>) -> Self {
-        Self {
-            state: Default::default(),
-            scan: prev.scan.activate(),
+    type Event = Event<'a>;
+
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        if self.state.open_mapping(&mut self.scan)? {
+            // Properties on the line of the mapping's first key belong to the key
+            let node = match self.scan.properties().line < self.state.mark.line {
+                true => self.scan.node(NodeKind::MappingStart, self.state.mark),
+                false => NodeKind::MappingStart.at(self.state.mark),
+            };
+            *output = Some(node.into());
         }
+
+        self.state.find_next(&mut self.scan)
     }
 }
 
-// macro_rules! from_start {
-//     ( $($type:ident),* ) => {
-//         $(
-//             impl<I: Iterator<Item = Byte>> From<StateMachine<I, Start>> for StateMachine<I, $type> {
+impl<'a, I> Drive for StateMachine<'a, I, MapVerifyKey>
+where
+    I: Iterator<Item = Byte>,
+{
+    type Event = Event<'a>;
 
-//                 fn from(prev: StateMachine<I, Start>) -> Self {
-//                     Self {
-//                         state: Default::default(),
-//                         scan: prev.scan,
-//                     }
-//                 }
-//             }
-//         )*
-//     };
-// }
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        self.state.parse_key(&mut self.scan)?;
 
-//from_start!();
+        let key = self.scan.resolve(take(&mut self.state.key))?;
+        let node = match self.state.alias {
+            true => NodeKind::Alias(key),
+            false => NodeKind::scalar(key, self.state.style),
+        };
+        *output = Some(self.scan.node(node, self.state.mark).into());
 
-macro_rules! from_linestart {
-    ( $($type:ident),* ) => {
-        $(
-            impl<I: Iterator<Item = Byte>> From<StateMachine<I, LineStart, Active>> for StateMachine<I, $type> {
+        self.state.find_next(&mut self.scan)
+    }
+}
 
+impl<'a, I> Drive for StateMachine<'a, I, MapWhiteSpace>
+where
+    I: Iterator<Item = Byte>,
+{
+    type Event = Event<'a>;
 
-                fn from(prev: StateMachine<I, LineStart, Active>) -> Self {
-                    Self {
-                        state: Default::default(),
-                        scan: prev.scan.deactivate(),
-                    }
-                }
-            }
-        )*
-    };
+    fn drive(&mut self, _: &mut Self::Event) -> Result<Marker> {
+        self.state
+            .parse_whitespace(&mut self.scan)
+            .and(self.state.find_next(&mut self.scan))
+    }
 }
 
-from_linestart!(LineEnd, AmbiguousScalar);
+impl<'a, I> Drive for StateMachine<'a, I, MapValue>
+where
+    I: Iterator<Item = Byte>,
+{
+    type Event = Event<'a>;
 
-// macro_rules! from_lineend {
-//     ( $($type:ident),* ) => {
-//         $(
-//             impl<I: Iterator<Item = Byte>> From<StateMachine<I, LineEnd>> for StateMachine<I, $type> {
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        self.state.parse_value(&mut self.scan)?;
 
-//                 fn from(prev: StateMachine<I, LineEnd>) -> Self {
-//                     Self {
-//                         state: Default::default(),
-//                         scan: prev.scan,
-//                     }
-//                 }
-//             }
-//         )*
-//     };
-// }
+        let value = self.scan.resolve(take(&mut self.state.value))?;
+        let node = NodeKind::scalar(value, ScalarStyle::Plain);
+        *output = Some(self.scan.node(node, self.state.mark).into());
+        self.scan.set_pending(false);
 
-//from_lineend!();
+        self.state.find_next(&mut self.scan)
+    }
+}
 
-impl<I> From<StateMachine<I, LineEnd>> for StateMachine<I, LineStart, Active>
+impl<'a, I> Drive for StateMachine<'a, I, MapEnd>
 where
     I: Iterator<Item = Byte>,
 {
-    fn from(prev: StateMachine<I, LineEnd>) -> Self {
-        Self {
-            state: Default::default(),
-            scan: prev.scan.activate(),
-        }
+    type Event = Event<'a>;
+
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        self.state.close(&mut self.scan)?;
+        *output = Some(NodeKind::MappingEnd.at(self.scan.mark()).into());
+
+        self.state.find_next(&mut self.scan)
     }
 }
 
-/* Ambiguous */
-macro_rules! from_ambi_scalar {
-    ( $($type:ident),* ) => {
-        $(
-            impl<I: Iterator<Item = Byte>> From<StateMachine<I, AmbiguousScalar>> for StateMachine<I, $type> {
+impl<'a, I> Drive for StateMachine<'a, I, MapExplicitKey, Active>
+where
+    I: Iterator<Item = Byte>,
+{
+    type Event = Event<'a>;
 
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        if self.state.parse_indicator(&mut self.scan)? {
+            // Properties on the line of the mapping's first key belong to the key
+            let node = match self.scan.properties().line < self.state.mark.line {
+                true => self.scan.node(NodeKind::MappingStart, self.state.mark),
+                false => NodeKind::MappingStart.at(self.state.mark),
+            };
+            *output = Some(node.into());
+        }
 
-                fn from(prev: StateMachine<I, AmbiguousScalar>) -> Self {
-                    Self {
-                        state: prev.state.into(),
-                        scan: prev.scan,
-                    }
-                }
-            }
-        )*
-    };
+        self.state.update_indent(&mut self.scan)?;
+        self.state.find_next(&mut self.scan)
+    }
 }
 
-from_ambi_scalar!(AmbiguousColon, ScalarLiteral);
+impl<'a, I> Drive for StateMachine<'a, I, MapExplicitValue, Active>
+where
+    I: Iterator<Item = Byte>,
+{
+    type Event = Event<'a>;
+
+    fn drive(&mut self, _: &mut Self::Event) -> Result<Marker> {
+        self.state.parse_indicator(&mut self.scan)?;
+        self.state.update_indent(&mut self.scan)?;
+        self.state.find_next(&mut self.scan)
+    }
+}
 
-impl<I: Iterator<Item = Byte>> From<StateMachine<I, AmbiguousColon>>
-    for StateMachine<I, AmbiguousScalar>
+/* Sequence Drivers */
+impl<'a, I> Drive for StateMachine<'a, I, SequenceStart>
+where
+    I: Iterator<Item = Byte>,
 {
-    fn from(prev: StateMachine<I, AmbiguousColon>) -> Self {
-        Self {
-            state: prev.state.into(),
-            scan: prev.scan,
+    type Event = Event<'a>;
+
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        if self.state.parse_indicator(&mut self.scan)? {
+            let node = self.scan.node(NodeKind::SequenceStart, self.state.mark);
+            *output = Some(node.into());
         }
+
+        self.state.find_next(&mut self.scan)
     }
 }
 
-impl<I: Iterator<Item = Byte>> From<StateMachine<I, AmbiguousColon>> for StateMachine<I, MapStart> {
-    fn from(prev: StateMachine<I, AmbiguousColon>) -> Self {
-        Self {
-            state: MapStart::extend_from(prev.scan.floor(), prev.state.scratch),
-            scan: prev.scan,
-        }
+impl<'a, I> Drive for StateMachine<'a, I, SequenceEntry, Active>
+where
+    I: Iterator<Item = Byte>,
+{
+    type Event = Event<'a>;
+
+    fn drive(&mut self, _: &mut Self::Event) -> Result<Marker> {
+        self.state
+            .update_indent(&mut self.scan)
+            .and(self.state.find_next(&mut self.scan))
     }
 }
 
-/* Scalar */
+impl<'a, I> Drive for StateMachine<'a, I, SequenceEnd>
+where
+    I: Iterator<Item = Byte>,
+{
+    type Event = Event<'a>;
 
-/* Map */
-impl<I: Iterator<Item = Byte>> From<StateMachine<I, MapStart>> for StateMachine<I, MapVerifyKey> {
-    fn from(prev: StateMachine<I, MapStart>) -> Self {
-        Self {
-            state: prev.state.into(),
-            scan: prev.scan,
-        }
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        self.state.close(&mut self.scan)?;
+        *output = Some(NodeKind::SequenceEnd.at(self.scan.mark()).into());
+
+        self.state.find_next(&mut self.scan)
     }
 }
 
-impl<I: Iterator<Item = Byte>> From<StateMachine<I, MapVerifyKey>>
-    for StateMachine<I, MapWhiteSpace>
+/* Flow Drivers */
+impl<'a, I> Drive for StateMachine<'a, I, FlowStart>
+where
+    I: Iterator<Item = Byte>,
 {
-    fn from(prev: StateMachine<I, MapVerifyKey>) -> Self {
-        Self {
-            state: prev.state.into(),
-            scan: prev.scan,
-        }
+    type Event = Event<'a>;
+
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        let node = match self.state.open(&mut self.scan)? {
+            LevelKind::Mapping => NodeKind::MappingStart,
+            LevelKind::Sequence => NodeKind::SequenceStart,
+        };
+        *output = Some(self.scan.node(node, self.state.mark).into());
+
+        self.state.find_next(&mut self.scan)
     }
 }
 
-impl<I: Iterator<Item = Byte>> From<StateMachine<I, MapWhiteSpace>> for StateMachine<I, MapValue> {
-    fn from(prev: StateMachine<I, MapWhiteSpace>) -> Self {
-        Self {
-            state: prev.state.into(),
-            scan: prev.scan,
+impl<'a, I> Drive for StateMachine<'a, I, FlowEntry>
+where
+    I: Iterator<Item = Byte>,
+{
+    type Event = Event<'a>;
+
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        let marker = self.state.find_next(&mut self.scan)?;
+
+        if take(&mut self.state.empty) {
+            let node = NodeKind::scalar("", ScalarStyle::Plain);
+            let mark = self.scan.mark();
+            *output = Some(self.scan.node(node, mark).into());
+        } else if let Some(comment) = take(&mut self.state.comment) {
+            *output = Some(comment.into());
         }
+
+        Ok(marker)
     }
 }
 
-impl<I: Iterator<Item = Byte>> From<StateMachine<I, MapValue>> for StateMachine<I, LineEnd> {
-    fn from(prev: StateMachine<I, MapValue>) -> Self {
-        Self {
-            state: Default::default(),
-            scan: prev.scan,
-        }
+impl<'a, I> Drive for StateMachine<'a, I, FlowScalar>
+where
+    I: Iterator<Item = Byte>,
+{
+    type Event = Event<'a>;
+
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        self.state.parse_scalar(&mut self.scan)?;
+
+        let scalar = self.scan.resolve(take(&mut self.state.scalar))?;
+        let node = match take(&mut self.state.alias) {
+            true => NodeKind::Alias(scalar),
+            false => NodeKind::scalar(scalar, ScalarStyle::Plain),
+        };
+        *output = Some(self.scan.node(node, self.state.mark).into());
+
+        self.state.find_next(&mut self.scan)
     }
 }
 
-/* Into Failure */
-impl<I, S> From<(Error, StateMachine<I, S>)> for StateMachine<I, Failure>
+impl<'a, I> Drive for StateMachine<'a, I, FlowEnd>
 where
     I: Iterator<Item = Byte>,
 {
-    fn from((err, prev): (Error, StateMachine<I, S>)) -> Self {
-        StateMachine {
-            state: err.into(),
-            scan: prev.scan,
-        }
+    type Event = Event<'a>;
+
+    fn drive(&mut self, output: &mut Self::Event) -> Result<Marker> {
+        let node = match self.state.close(&mut self.scan)? {
+            LevelKind::Mapping => NodeKind::MappingEnd,
+            LevelKind::Sequence => NodeKind::SequenceEnd,
+        };
+        *output = Some(node.at(self.state.mark).into());
+
+        self.state.find_next(&mut self.scan)
     }
 }
 
-impl<I, S> From<(Error, StateMachine<I, S, Active>)> for StateMachine<I, Failure>
+/* Legal state transitions */
+/* ======================= */
+impl<'a, I> From<StateMachine<'a, I, Start>> for StateMachine<'a, I, DocumentStart, Active>
 where
     I: Iterator<Item = Byte>,
 {
-    fn from((err, prev): (Error, StateMachine<I, S, Active>)) -> Self {
-        StateMachine {
-            state: err.into(),
-            scan: prev.scan.deactivate(),
+    fn from(prev: StateMachine<'a, I, Start>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan.activate(),
         }
     }
 }
 
-/* Into Done */
-macro_rules! to_done {
+macro_rules! from_start {
     ( $($type:ident),* ) => {
         $(
-            impl<I: Iterator<Item = Byte>> From<StateMachine<I, $type>> for StateMachine<I, Done> {
-
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, Start>> for StateMachine<'a, I, $type> {
 
-                fn from(prev: StateMachine<I, $type>) -> Self {
+                fn from(prev: StateMachine<'a, I, Start>) -> Self {
                     Self {
                         state: Default::default(),
                         scan: prev.scan,
@@ -397,15 +590,14 @@ macro_rules! to_done {
     };
 }
 
-to_done!(Start, LineEnd, ScalarLiteral, MapValue);
+from_start!(StreamEnd);
 
-macro_rules! to_done_deactivate {
+macro_rules! from_document_start {
     ( $($type:ident),* ) => {
         $(
-            impl<I: Iterator<Item = Byte>> From<StateMachine<I, $type, Active>> for StateMachine<I, Done> {
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, DocumentStart, Active>> for StateMachine<'a, I, $type> {
 
-
-                fn from(prev: StateMachine<I, $type, Active>) -> Self {
+                fn from(prev: StateMachine<'a, I, DocumentStart, Active>) -> Self {
                     Self {
                         state: Default::default(),
                         scan: prev.scan.deactivate(),
@@ -416,10 +608,833 @@ macro_rules! to_done_deactivate {
     };
 }
 
-to_done_deactivate!(LineStart);
-
-/* Helper */
+from_document_start!(
+    AmbiguousScalar,
+    ScalarQuoted,
+    ScalarBlock,
+    SequenceStart,
+    FlowStart,
+    DocumentEnd,
+    StreamEnd,
+    NodeProperties,
+    Alias
+);
 
-fn take<T: Default>(dest: &mut T) -> T {
-    std::mem::replace(dest, T::default())
+impl<'a, I> From<StateMachine<'a, I, DocumentEnd>> for StateMachine<'a, I, DocumentStart, Active>
+where
+    I: Iterator<Item = Byte>,
+{
+    fn from(prev: StateMachine<'a, I, DocumentEnd>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan.activate(),
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, DocumentEnd>>
+    for StateMachine<'a, I, StreamEnd>
+{
+    fn from(prev: StateMachine<'a, I, DocumentEnd>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan,
+        }
+    }
+}
+
+macro_rules! from_linestart {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, LineStart, Active>> for StateMachine<'a, I, $type> {
+
+
+                fn from(prev: StateMachine<'a, I, LineStart, Active>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan.deactivate(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_linestart!(
+    LineEnd,
+    AmbiguousScalar,
+    ScalarQuoted,
+    ScalarBlock,
+    SequenceStart,
+    SequenceEnd,
+    FlowStart,
+    MapEnd,
+    NodeProperties,
+    Alias
+);
+
+macro_rules! from_lineend {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, LineEnd>> for StateMachine<'a, I, $type> {
+
+                fn from(prev: StateMachine<'a, I, LineEnd>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_lineend!(SequenceEnd, MapEnd);
+
+impl<'a, I> From<StateMachine<'a, I, LineEnd>> for StateMachine<'a, I, LineStart, Active>
+where
+    I: Iterator<Item = Byte>,
+{
+    fn from(prev: StateMachine<'a, I, LineEnd>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan.activate(),
+        }
+    }
+}
+
+/* Ambiguous */
+macro_rules! from_node_properties {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, NodeProperties>> for StateMachine<'a, I, $type> {
+
+                fn from(prev: StateMachine<'a, I, NodeProperties>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_node_properties!(LineEnd, ScalarBlock, FlowStart, AmbiguousScalar);
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, NodeProperties>>
+    for StateMachine<'a, I, ScalarQuoted>
+{
+    fn from(prev: StateMachine<'a, I, NodeProperties>) -> Self {
+        Self {
+            state: prev.state.into(),
+            scan: prev.scan,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, NodeProperties>>
+    for StateMachine<'a, I, MapValue>
+{
+    fn from(prev: StateMachine<'a, I, NodeProperties>) -> Self {
+        Self {
+            state: prev.state.into(),
+            scan: prev.scan,
+        }
+    }
+}
+
+macro_rules! from_alias {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, Alias>> for StateMachine<'a, I, $type> {
+
+                fn from(prev: StateMachine<'a, I, Alias>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_alias!(LineEnd, SequenceEnd, MapEnd);
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, Alias>>
+    for StateMachine<'a, I, MapStart>
+{
+    fn from(prev: StateMachine<'a, I, Alias>) -> Self {
+        let mut state = MapStart::extend_from(
+            prev.scan.current(),
+            prev.state.name,
+            ScalarStyle::Plain,
+            prev.state.mark,
+        );
+        state.alias = true;
+
+        Self {
+            state,
+            scan: prev.scan,
+        }
+    }
+}
+
+/// A missing node may be found wherever a line's node is, or where the
+/// stream ends
+macro_rules! to_empty {
+    ( $($from:ident),* ; $($active:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, $from>> for StateMachine<'a, I, EmptyNode> {
+                fn from(prev: StateMachine<'a, I, $from>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, $active, Active>> for StateMachine<'a, I, EmptyNode> {
+                fn from(prev: StateMachine<'a, I, $active, Active>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan.deactivate(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+to_empty!(
+    LineEnd,
+    NodeProperties,
+    Alias,
+    ScalarLiteral,
+    ScalarQuoted,
+    ScalarBlock,
+    MapWhiteSpace,
+    MapValue,
+    MapEnd,
+    SequenceEnd,
+    FlowEnd;
+    LineStart,
+    SequenceEntry,
+    MapExplicitKey,
+    MapExplicitValue
+);
+
+macro_rules! from_empty {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, EmptyNode>> for StateMachine<'a, I, $type> {
+                fn from(prev: StateMachine<'a, I, EmptyNode>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_empty!(
+    AmbiguousScalar,
+    ScalarQuoted,
+    ScalarBlock,
+    SequenceStart,
+    SequenceEnd,
+    FlowStart,
+    MapEnd,
+    DocumentEnd,
+    NodeProperties,
+    Alias
+);
+
+macro_rules! from_ambi_scalar {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, AmbiguousScalar>> for StateMachine<'a, I, $type> {
+
+
+                fn from(prev: StateMachine<'a, I, AmbiguousScalar>) -> Self {
+                    Self {
+                        state: prev.state.into(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_ambi_scalar!(AmbiguousColon, ScalarLiteral);
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, AmbiguousColon>>
+    for StateMachine<'a, I, AmbiguousScalar>
+{
+    fn from(prev: StateMachine<'a, I, AmbiguousColon>) -> Self {
+        Self {
+            state: prev.state.into(),
+            scan: prev.scan,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, AmbiguousColon>>
+    for StateMachine<'a, I, MapStart>
+{
+    fn from(prev: StateMachine<'a, I, AmbiguousColon>) -> Self {
+        Self {
+            state: MapStart::extend_from(
+                prev.scan.current(),
+                prev.state.scratch,
+                ScalarStyle::Plain,
+                prev.state.mark,
+            ),
+            scan: prev.scan,
+        }
+    }
+}
+
+/* Scalar */
+macro_rules! from_scalar_literal {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, ScalarLiteral>> for StateMachine<'a, I, $type> {
+
+                fn from(prev: StateMachine<'a, I, ScalarLiteral>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_scalar_literal!(LineEnd, SequenceEnd, MapEnd);
+
+impl<'a, I> From<StateMachine<'a, I, ScalarLiteral>> for StateMachine<'a, I, LineStart, Active>
+where
+    I: Iterator<Item = Byte>,
+{
+    fn from(prev: StateMachine<'a, I, ScalarLiteral>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan.activate(),
+        }
+    }
+}
+
+macro_rules! from_scalar_quoted {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, ScalarQuoted>> for StateMachine<'a, I, $type> {
+
+                fn from(prev: StateMachine<'a, I, ScalarQuoted>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_scalar_quoted!(LineEnd, SequenceEnd, MapEnd, FlowEntry);
+
+macro_rules! from_scalar_block {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, ScalarBlock>> for StateMachine<'a, I, $type> {
+
+                fn from(prev: StateMachine<'a, I, ScalarBlock>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_scalar_block!(SequenceEnd, MapEnd);
+
+impl<'a, I> From<StateMachine<'a, I, ScalarBlock>> for StateMachine<'a, I, LineStart, Active>
+where
+    I: Iterator<Item = Byte>,
+{
+    fn from(prev: StateMachine<'a, I, ScalarBlock>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan.activate(),
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, ScalarQuoted>>
+    for StateMachine<'a, I, MapStart>
+{
+    fn from(prev: StateMachine<'a, I, ScalarQuoted>) -> Self {
+        let style = prev.state.style();
+
+        Self {
+            state: MapStart::extend_from(
+                prev.scan.current(),
+                prev.state.scalar,
+                style,
+                prev.state.mark,
+            ),
+            scan: prev.scan,
+        }
+    }
+}
+
+/* Sequence */
+impl<'a, I> From<StateMachine<'a, I, SequenceStart>> for StateMachine<'a, I, SequenceEntry, Active>
+where
+    I: Iterator<Item = Byte>,
+{
+    fn from(prev: StateMachine<'a, I, SequenceStart>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan.activate(),
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, SequenceStart>>
+    for StateMachine<'a, I, AmbiguousScalar>
+{
+    fn from(prev: StateMachine<'a, I, SequenceStart>) -> Self {
+        Self {
+            state: prev.state.into(),
+            scan: prev.scan,
+        }
+    }
+}
+
+macro_rules! from_sequence_entry {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, SequenceEntry, Active>> for StateMachine<'a, I, $type> {
+
+                fn from(prev: StateMachine<'a, I, SequenceEntry, Active>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan.deactivate(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_sequence_entry!(
+    LineEnd,
+    AmbiguousScalar,
+    ScalarQuoted,
+    ScalarBlock,
+    SequenceStart,
+    FlowStart,
+    NodeProperties,
+    Alias
+);
+
+macro_rules! from_sequence_end {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, SequenceEnd>> for StateMachine<'a, I, $type> {
+
+                fn from(prev: StateMachine<'a, I, SequenceEnd>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_sequence_end!(
+    AmbiguousScalar,
+    ScalarQuoted,
+    ScalarBlock,
+    SequenceStart,
+    FlowStart,
+    MapEnd,
+    NodeProperties,
+    Alias
+);
+
+/* Map */
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, MapStart>>
+    for StateMachine<'a, I, MapVerifyKey>
+{
+    fn from(prev: StateMachine<'a, I, MapStart>) -> Self {
+        Self {
+            state: prev.state.into(),
+            scan: prev.scan,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, MapVerifyKey>>
+    for StateMachine<'a, I, MapWhiteSpace>
+{
+    fn from(prev: StateMachine<'a, I, MapVerifyKey>) -> Self {
+        Self {
+            state: prev.state.into(),
+            scan: prev.scan,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, MapWhiteSpace>>
+    for StateMachine<'a, I, ScalarQuoted>
+{
+    fn from(prev: StateMachine<'a, I, MapWhiteSpace>) -> Self {
+        Self {
+            state: prev.state.into(),
+            scan: prev.scan,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, MapWhiteSpace>>
+    for StateMachine<'a, I, ScalarBlock>
+{
+    fn from(prev: StateMachine<'a, I, MapWhiteSpace>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, MapWhiteSpace>>
+    for StateMachine<'a, I, FlowStart>
+{
+    fn from(prev: StateMachine<'a, I, MapWhiteSpace>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, MapWhiteSpace>>
+    for StateMachine<'a, I, MapValue>
+{
+    fn from(prev: StateMachine<'a, I, MapWhiteSpace>) -> Self {
+        Self {
+            state: prev.state.into(),
+            scan: prev.scan,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, MapWhiteSpace>>
+    for StateMachine<'a, I, LineEnd>
+{
+    fn from(prev: StateMachine<'a, I, MapWhiteSpace>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, MapWhiteSpace>>
+    for StateMachine<'a, I, NodeProperties>
+{
+    fn from(prev: StateMachine<'a, I, MapWhiteSpace>) -> Self {
+        Self {
+            state: prev.state.into(),
+            scan: prev.scan,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, MapWhiteSpace>>
+    for StateMachine<'a, I, Alias>
+{
+    fn from(prev: StateMachine<'a, I, MapWhiteSpace>) -> Self {
+        Self {
+            state: prev.state.into(),
+            scan: prev.scan,
+        }
+    }
+}
+
+macro_rules! from_map_value {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, MapValue>> for StateMachine<'a, I, $type> {
+
+                fn from(prev: StateMachine<'a, I, MapValue>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_map_value!(LineEnd, SequenceEnd, MapEnd);
+
+impl<'a, I> From<StateMachine<'a, I, MapValue>> for StateMachine<'a, I, LineStart, Active>
+where
+    I: Iterator<Item = Byte>,
+{
+    fn from(prev: StateMachine<'a, I, MapValue>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan.activate(),
+        }
+    }
+}
+
+macro_rules! from_map_end {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, MapEnd>> for StateMachine<'a, I, $type> {
+
+                fn from(prev: StateMachine<'a, I, MapEnd>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_map_end!(
+    AmbiguousScalar,
+    ScalarQuoted,
+    ScalarBlock,
+    SequenceStart,
+    SequenceEnd,
+    FlowStart,
+    NodeProperties,
+    Alias
+);
+
+macro_rules! from_explicit {
+    ( $from:ident => $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, $from, Active>> for StateMachine<'a, I, $type> {
+
+                fn from(prev: StateMachine<'a, I, $from, Active>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan.deactivate(),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_explicit!(MapExplicitKey =>
+    LineEnd,
+    AmbiguousScalar,
+    ScalarQuoted,
+    ScalarBlock,
+    SequenceStart,
+    FlowStart,
+    NodeProperties,
+    Alias
+);
+
+from_explicit!(MapExplicitValue =>
+    LineEnd,
+    AmbiguousScalar,
+    ScalarQuoted,
+    ScalarBlock,
+    SequenceStart,
+    FlowStart,
+    NodeProperties,
+    Alias
+);
+
+/// Explicit keys and values start after a line's indentation, keys
+/// also after the indicator of the entry or value they are the node of
+macro_rules! to_explicit {
+    ( $to:ident: $($from:ident),* ; $($active:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, $from>> for StateMachine<'a, I, $to, Active> {
+
+                fn from(prev: StateMachine<'a, I, $from>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan.activate(),
+                    }
+                }
+            }
+        )*
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, $active, Active>> for StateMachine<'a, I, $to, Active> {
+
+                fn from(prev: StateMachine<'a, I, $active, Active>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+to_explicit!(MapExplicitKey: MapEnd, SequenceEnd, EmptyNode; LineStart, DocumentStart, SequenceEntry, MapExplicitValue);
+to_explicit!(MapExplicitValue: MapEnd, SequenceEnd, EmptyNode; LineStart, DocumentStart);
+
+/* Flow */
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, FlowStart>>
+    for StateMachine<'a, I, FlowEntry>
+{
+    fn from(prev: StateMachine<'a, I, FlowStart>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan,
+        }
+    }
+}
+
+macro_rules! from_flow_entry {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, FlowEntry>> for StateMachine<'a, I, $type> {
+
+                fn from(prev: StateMachine<'a, I, FlowEntry>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_flow_entry!(FlowStart, FlowScalar, FlowEnd, ScalarQuoted);
+
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, FlowScalar>>
+    for StateMachine<'a, I, FlowEntry>
+{
+    fn from(prev: StateMachine<'a, I, FlowScalar>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan,
+        }
+    }
+}
+
+macro_rules! from_flow_end {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, FlowEnd>> for StateMachine<'a, I, $type> {
+
+                fn from(prev: StateMachine<'a, I, FlowEnd>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_flow_end!(FlowEntry, LineEnd, SequenceEnd, MapEnd);
+
+/* Into Failure */
+impl<'a, I, S> From<(Error, StateMachine<'a, I, S>)> for StateMachine<'a, I, Failure>
+where
+    I: Iterator<Item = Byte>,
+{
+    fn from((err, prev): (Error, StateMachine<'a, I, S>)) -> Self {
+        StateMachine {
+            state: err.at(prev.scan.mark()).into(),
+            scan: prev.scan,
+        }
+    }
+}
+
+impl<'a, I, S> From<(Error, StateMachine<'a, I, S, Active>)> for StateMachine<'a, I, Failure>
+where
+    I: Iterator<Item = Byte>,
+{
+    fn from((err, prev): (Error, StateMachine<'a, I, S, Active>)) -> Self {
+        StateMachine {
+            state: err.at(prev.scan.mark()).into(),
+            scan: prev.scan.deactivate(),
+        }
+    }
+}
+
+/* Into DocumentEnd */
+macro_rules! to_document_end {
+    ( $($type:ident),* ) => {
+        $(
+            impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, $type>> for StateMachine<'a, I, DocumentEnd> {
+
+                fn from(prev: StateMachine<'a, I, $type>) -> Self {
+                    Self {
+                        state: Default::default(),
+                        scan: prev.scan,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+to_document_end!(
+    LineEnd,
+    ScalarLiteral,
+    ScalarQuoted,
+    ScalarBlock,
+    MapValue,
+    MapEnd,
+    SequenceEnd,
+    FlowEnd,
+    Alias
+);
+
+impl<'a, I> From<StateMachine<'a, I, LineStart, Active>> for StateMachine<'a, I, DocumentEnd>
+where
+    I: Iterator<Item = Byte>,
+{
+    fn from(prev: StateMachine<'a, I, LineStart, Active>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan.deactivate(),
+        }
+    }
+}
+
+/* Into Done */
+impl<'a, I: Iterator<Item = Byte>> From<StateMachine<'a, I, StreamEnd>>
+    for StateMachine<'a, I, Done>
+{
+    fn from(prev: StateMachine<'a, I, StreamEnd>) -> Self {
+        Self {
+            state: Default::default(),
+            scan: prev.scan,
+        }
+    }
 }
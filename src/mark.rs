@@ -0,0 +1,34 @@
+use std::fmt::{self, Display};
+
+/// A position in the stream, lines and columns start at zero
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Mark {
+    pub line: usize,
+    // Counted in characters
+    pub column: usize,
+    // Byte offset from the start of the stream, once transcoded to UTF-8
+    pub index: usize,
+}
+
+impl Mark {
+    /// Advances the mark past the given UTF-8 byte
+    pub(super) fn advance(&mut self, ch: u8) {
+        self.index += 1;
+
+        match ch {
+            b'\n' => {
+                self.line += 1;
+                self.column = 0;
+            }
+            // Continuation bytes belong to the preceding character
+            0x80..=0xBF => {}
+            _ => self.column += 1,
+        }
+    }
+}
+
+impl Display for Mark {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {} column {}", self.line + 1, self.column + 1)
+    }
+}
@@ -1,5 +1,174 @@
-#[derive(Debug)]
-pub(super) enum NodeKind {
-    Key(Vec<u8>),
-    ScalarPlain(Vec<u8>),
+use std::{
+    borrow::Cow,
+    fmt::{self, Display},
+};
+
+use super::mark::Mark;
+
+/// A node yielded by the parser, and where it starts in the stream.
+/// Nodes parsed from a slice may borrow from it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node<'a> {
+    pub kind: NodeKind<'a>,
+    pub mark: Mark,
+    /// The anchor given to a scalar or collection, which later
+    /// [`NodeKind::Alias`] nodes may refer to it by
+    pub anchor: Option<Cow<'a, str>>,
+    /// The tag given to a scalar or collection, resolved to a full URI.
+    /// Local tags keep their leading '!', and the non-specific '!' alone
+    /// marks the node as a string, sequence or mapping whatever its content
+    pub tag: Option<Cow<'a, str>>,
+}
+
+impl Node<'_> {
+    /// Copies any content borrowed from the input
+    pub fn into_owned(self) -> Node<'static> {
+        Node {
+            kind: self.kind.into_owned(),
+            mark: self.mark,
+            anchor: self.anchor.map(|anchor| anchor.into_owned().into()),
+            tag: self.tag.map(|tag| tag.into_owned().into()),
+        }
+    }
+}
+
+/// Nodes compare equal to their kind, regardless of position
+impl<'b> PartialEq<NodeKind<'b>> for Node<'_> {
+    fn eq(&self, other: &NodeKind<'b>) -> bool {
+        self.kind == *other
+    }
+}
+
+/// Describes the YAML nodes (and structural markers) yielded by the parser,
+/// in the order they occur in the stream
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeKind<'a> {
+    StreamStart,
+    StreamEnd,
+    /// Holds the directives preceding the document
+    DocumentStart(Directives),
+    DocumentEnd,
+    MappingStart,
+    MappingEnd,
+    SequenceStart,
+    SequenceEnd,
+    /// Borrowed from the input where possible, that is when the parser
+    /// reads from a slice and the scalar needed no unescaping or folding
+    Scalar {
+        value: Cow<'a, str>,
+        style: ScalarStyle,
+    },
+    Alias(Cow<'a, str>),
+    /// Only emitted if enabled in the parser's config, holds
+    /// everything following the '#' up to the end of the line
+    Comment(Cow<'a, str>),
+}
+
+impl<'a> NodeKind<'a> {
+    pub(super) fn scalar(value: impl Into<Cow<'a, str>>, style: ScalarStyle) -> Self {
+        Self::Scalar {
+            value: value.into(),
+            style,
+        }
+    }
+
+    /// Attaches the position the node starts at
+    pub(super) fn at(self, mark: Mark) -> Node<'a> {
+        Node {
+            kind: self,
+            mark,
+            anchor: None,
+            tag: None,
+        }
+    }
+
+    /// Describes the node for error messages
+    pub(super) fn describe(&self) -> &'static str {
+        match self {
+            Self::StreamStart => "the start of the stream",
+            Self::StreamEnd => "the end of the stream",
+            Self::DocumentStart(_) => "the start of a document",
+            Self::DocumentEnd => "the end of a document",
+            Self::MappingStart => "a mapping",
+            Self::MappingEnd => "the end of a mapping",
+            Self::SequenceStart => "a sequence",
+            Self::SequenceEnd => "the end of a sequence",
+            Self::Scalar { .. } => "a scalar",
+            Self::Alias(_) => "an alias",
+            Self::Comment(_) => "a comment",
+        }
+    }
+
+    /// Copies any content borrowed from the input
+    pub fn into_owned(self) -> NodeKind<'static> {
+        match self {
+            Self::StreamStart => NodeKind::StreamStart,
+            Self::StreamEnd => NodeKind::StreamEnd,
+            Self::DocumentStart(directives) => NodeKind::DocumentStart(directives),
+            Self::DocumentEnd => NodeKind::DocumentEnd,
+            Self::MappingStart => NodeKind::MappingStart,
+            Self::MappingEnd => NodeKind::MappingEnd,
+            Self::SequenceStart => NodeKind::SequenceStart,
+            Self::SequenceEnd => NodeKind::SequenceEnd,
+            Self::Scalar { value, style } => NodeKind::scalar(value.into_owned(), style),
+            Self::Alias(name) => NodeKind::Alias(name.into_owned().into()),
+            Self::Comment(text) => NodeKind::Comment(text.into_owned().into()),
+        }
+    }
+}
+
+/// The presentation style of a scalar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarStyle {
+    Plain,
+    SingleQuoted,
+    DoubleQuoted,
+    Literal,
+    Folded,
+}
+
+/// The directives preceding a document
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Directives {
+    /// The YAML version given by a %YAML directive
+    pub version: Option<Version>,
+    /// The handles registered by %TAG directives and the prefixes they
+    /// stand for, in the order given
+    pub tags: Vec<(String, String)>,
+}
+
+impl Directives {
+    pub fn is_empty(&self) -> bool {
+        self.version.is_none() && self.tags.is_empty()
+    }
+
+    /// The prefix the given tag handle stands for, if any. Unless
+    /// registered otherwise, the primary handle '!' stands for '!' and
+    /// the secondary handle '!!' for 'tag:yaml.org,2002:'
+    pub fn prefix(&self, handle: &str) -> Option<&str> {
+        let registered = self
+            .tags
+            .iter()
+            .find(|(registered, _)| registered == handle)
+            .map(|(_, prefix)| prefix.as_str());
+
+        match handle {
+            "!" => registered.or(Some("!")),
+            "!!" => registered.or(Some("tag:yaml.org,2002:")),
+            _ => registered,
+        }
+    }
+}
+
+/// A YAML version, as given by a %YAML directive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
 }
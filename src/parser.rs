@@ -0,0 +1,90 @@
+use std::io;
+
+use super::{
+    config::ParserConfig,
+    document::Documents,
+    error::{Error, Result},
+    node::Node,
+    Handle,
+};
+
+/// YAML parser, yields the nodes of the underlying stream
+/// in the order they are encountered
+pub struct Parser<'a, R> {
+    handle: Handle<'a, R>,
+}
+
+impl<R> Parser<'_, R>
+where
+    R: io::Read,
+{
+    /// Create a parser which reads from the given stream
+    pub fn from_reader(reader: R) -> Self {
+        Self {
+            handle: Handle::new(reader),
+        }
+    }
+
+    /// Create a parser which reads from the given stream,
+    /// configured by the given options
+    pub fn with_config(reader: R, config: ParserConfig) -> Self {
+        Self {
+            handle: Handle::with_config(reader, config),
+        }
+    }
+}
+
+impl<'a, R> Parser<'a, R> {
+    /// Problems the parser has continued past so far, such as
+    /// an unknown minor version in a %YAML directive
+    pub fn warnings(&self) -> &[Error] {
+        &self.handle.warnings
+    }
+
+    /// Groups the remaining nodes of the stream by document
+    pub fn documents(self) -> Documents<'a, R> {
+        Documents::new(self.handle)
+    }
+}
+
+impl<'a> Parser<'a, &'a [u8]> {
+    /// Create a parser over the given byte slice. Scalars which need no
+    /// unescaping or folding are borrowed from it, if it is UTF-8
+    pub fn from_slice(slice: &'a [u8]) -> Self {
+        Self {
+            handle: Handle::from_slice(slice),
+        }
+    }
+
+    /// Create a parser over the given byte slice, configured by
+    /// the given options. Scalars are borrowed as by [`Parser::from_slice`]
+    pub fn from_slice_with_config(slice: &'a [u8], config: ParserConfig) -> Self {
+        Self {
+            handle: Handle::from_slice_with_config(slice, config),
+        }
+    }
+
+    /// Create a parser over the given string, borrowing scalars from it
+    /// where possible
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &'a str) -> Self {
+        Self::from_slice(s.as_bytes())
+    }
+
+    /// Create a parser over the given string, configured by the given
+    /// options, borrowing scalars from it where possible
+    pub fn from_str_with_config(s: &'a str, config: ParserConfig) -> Self {
+        Self::from_slice_with_config(s.as_bytes(), config)
+    }
+}
+
+impl<'a, R> Iterator for Parser<'a, R>
+where
+    R: io::Read,
+{
+    type Item = Result<Node<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.handle.next_node()
+    }
+}
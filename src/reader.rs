@@ -0,0 +1,68 @@
+use {super::scanner::Byte, std::io};
+
+/// Size of the chunks read from the underlying stream
+const CHUNK: usize = 8 * 1024;
+
+/// Reads the underlying stream in chunks, yielding it byte by byte
+/// without a read call per byte
+#[derive(Debug)]
+pub(super) struct Reader<R> {
+    inner: R,
+    buffer: Box<[u8]>,
+    // Unread bytes are buffer[pos..len]
+    pos: usize,
+    len: usize,
+}
+
+impl<R> Reader<R>
+where
+    R: io::Read,
+{
+    pub(super) fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buffer: vec![0; CHUNK].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    /// Refills the buffer, returning false at the end of the stream
+    fn fill(&mut self) -> io::Result<bool> {
+        loop {
+            match self.inner.read(&mut self.buffer) {
+                Ok(len) => {
+                    self.pos = 0;
+                    self.len = len;
+
+                    return Ok(len > 0);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl<R> Iterator for Reader<R>
+where
+    R: io::Read,
+{
+    type Item = Byte;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.len {
+            match self.fill() {
+                Ok(true) => {}
+                Ok(false) => return None,
+                Err(e) => return Some(Err(e)),
+            }
+        }
+
+        let byte = self.buffer[self.pos];
+        self.pos += 1;
+
+        Some(Ok(byte))
+    }
+}
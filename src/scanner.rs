@@ -1,73 +1,275 @@
-use {super::error::Result, std::io};
+use {
+    super::{
+        config::ParserConfig,
+        encoding::Decoder,
+        error::{ErrorKind, Result},
+        mark::Mark,
+        node::{Directives, Node, NodeKind},
+        scratch::Scratch,
+    },
+    std::{
+        borrow::Cow, collections::VecDeque, convert::TryFrom, io, marker::PhantomData, mem::take,
+    },
+};
 
 pub(super) type Byte = io::Result<u8>;
 
 #[derive(Debug)]
-pub(super) struct Scan<I, S = Inactive> {
-    ch: Option<u8>,
-    iter: I,
+pub(super) struct Scan<'a, I, S = Inactive> {
+    // Bytes read ahead of the scanner's position
+    window: VecDeque<u8>,
+    // Yields the stream transcoded to UTF-8
+    iter: Decoder<I>,
+    // The input, if it can be borrowed from
+    source: Option<&'a [u8]>,
+    mark: Mark,
+    config: ParserConfig,
     indent: IndentTrack<S>,
+    // Properties waiting for the node they belong to
+    properties: Properties<'a>,
+    // Index the current document's bytes are counted from
+    document: usize,
+    // Documents started so far
+    documents: usize,
+    // Set once the current document's root node has started
+    root: bool,
+    // Directives of the current document, which its tags are resolved with
+    directives: Directives,
 }
 
-impl<I, S> Scan<I, S>
+impl<'a, I, S> Scan<'a, I, S>
 where
     I: Iterator<Item = Byte>,
 {
-    pub(super) fn next(&mut self) -> Result<Option<u8>> {
-        match self.ch.take() {
-            ch @ Some(_) => Ok(ch),
-            None => match self.iter.next() {
-                Some(Err(e)) => Err(e)?,
-                Some(Ok(ch)) => Ok(Some(ch)),
-                None => Ok(None),
-            },
+    /// The next unconsumed byte, if any
+    #[inline]
+    pub(super) fn peak(&mut self) -> Result<Option<u8>> {
+        match self.window.front() {
+            Some(&ch) => Ok(Some(ch)),
+            None => self.peek_n(0),
         }
     }
 
-    pub(super) fn peak(&mut self) -> Result<Option<u8>> {
-        match self.ch {
-            ch @ Some(_) => Ok(ch),
-            None => match self.iter.next() {
-                Some(Err(e)) => Err(e)?,
-                Some(Ok(ch)) => {
-                    self.ch = Some(ch);
-                    Ok(self.ch)
-                }
-                None => Ok(None),
-            },
+    /// Looks ahead to the byte n places after the next unconsumed byte,
+    /// reading the stream up to it if necessary
+    pub(super) fn peek_n(&mut self, n: usize) -> Result<Option<u8>> {
+        while self.window.len() <= n {
+            match self.iter.next() {
+                Some(ch) => self.window.push_back(ch?),
+                None => break,
+            }
+
+            let limit = self.config.limits.document;
+
+            if self.mark.index + self.window.len() - self.document > limit {
+                Err(ErrorKind::DocumentLimit(limit))?
+            }
         }
+
+        Ok(self.window.get(n).copied())
     }
 
+    #[inline]
     pub(super) fn discard(&mut self) {
-        self.ch = None;
+        if let Some(ch) = self.window.pop_front() {
+            self.mark.advance(ch)
+        }
+    }
+
+    /// Consumes the next byte, adding it to the given scalar content
+    #[inline]
+    pub(super) fn take(&mut self, scratch: &mut Scratch) -> Result<()> {
+        if let Some(&ch) = self.window.front() {
+            self.check_scalar(scratch.len() + 1)?;
+            let borrow = self.source.is_some() && self.iter.utf8_offset().is_some();
+
+            scratch.extend_from(ch, self.mark.index, borrow);
+            self.discard()
+        }
+
+        Ok(())
+    }
+
+    /// Adds a byte which isn't read verbatim from the input to the
+    /// given scalar content
+    pub(super) fn push(&self, scratch: &mut Scratch, ch: u8) -> Result<()> {
+        self.check_scalar(scratch.len() + 1)?;
+        scratch.push(ch);
+
+        Ok(())
+    }
+
+    /// Adds bytes which aren't read verbatim from the input to the
+    /// given scalar content
+    pub(super) fn extend<B>(&self, scratch: &mut Scratch, bytes: B) -> Result<()>
+    where
+        B: IntoIterator<Item = u8>,
+    {
+        let bytes = bytes.into_iter();
+        self.check_scalar(scratch.len().saturating_add(bytes.size_hint().0))?;
+        scratch.extend(bytes);
+
+        self.check_scalar(scratch.len())
+    }
+
+    /// Checks scalar content of the given length against the limit
+    fn check_scalar(&self, len: usize) -> Result<()> {
+        let limit = self.config.limits.scalar;
+
+        match len <= limit {
+            true => Ok(()),
+            false => Err(ErrorKind::ScalarLimit(limit))?,
+        }
+    }
+
+    /// Resolves scalar content, borrowing it from the input if possible
+    pub(super) fn resolve(&self, scratch: Scratch) -> Result<Cow<'a, str>> {
+        self.check_scalar(scratch.len())?;
+
+        let source = self
+            .source
+            .zip(self.iter.utf8_offset())
+            .and_then(|(source, offset)| source.get(offset..));
+
+        scratch.into_str(source)
     }
 
+    /// Position of the next unconsumed byte
+    pub(super) fn mark(&self) -> Mark {
+        self.mark
+    }
+
+    /// Column of the next unconsumed byte in the current line
+    pub(super) fn column(&self) -> u16 {
+        u16::try_from(self.mark.column).unwrap_or(u16::MAX)
+    }
+
+    pub(super) fn config(&self) -> &ParserConfig {
+        &self.config
+    }
+
+    /// Indentation of the current line's content
     pub(super) fn current(&self) -> u16 {
         self.indent.current()
     }
 
-    pub(super) fn previous(&self) -> u16 {
-        self.indent.previous()
+    /// The innermost open block collection, if any
+    pub(super) fn level(&self) -> Option<&Level> {
+        self.indent.history().last()
+    }
+
+    /// Opens a new block collection at the current indentation,
+    /// which becomes the node of its parent's pending key or entry
+    pub(super) fn open(&mut self, kind: LevelKind) -> Result<()> {
+        self.check_depth()?;
+        self.indent.open(kind);
+
+        Ok(())
+    }
+
+    /// Opens a new flow collection, nested in the innermost open collection
+    pub(super) fn open_flow(&mut self, kind: LevelKind) -> Result<()> {
+        self.check_depth()?;
+        self.indent.open_flow(kind);
+
+        Ok(())
+    }
+
+    fn check_depth(&self) -> Result<()> {
+        let limit = self.config.limits.depth;
+
+        match self.indent.history().len() < limit {
+            true => Ok(()),
+            false => Err(ErrorKind::DepthLimit(limit))?,
+        }
+    }
+
+    /// Counts a new document against the stream's limit, its tags
+    /// are resolved with the given directives
+    pub(super) fn start_document(&mut self, directives: Directives) -> Result<()> {
+        let limit = self.config.limits.documents;
+        self.documents += 1;
+        self.directives = directives;
+
+        match self.documents <= limit {
+            true => Ok(()),
+            false => Err(ErrorKind::DocumentCountLimit(limit))?,
+        }
+    }
+
+    /// Counts the bytes of the next document from the current position
+    pub(super) fn end_document(&mut self) {
+        self.document = self.mark.index;
+        self.root = false;
+    }
+
+    /// Closes the innermost open collection
+    pub(super) fn close(&mut self) -> Option<Level> {
+        self.indent.close()
     }
 
-    pub(super) fn floor(&self) -> u16 {
-        self.indent.floor()
+    /// Position within the innermost collection's entries, if it is a
+    /// flow collection
+    pub(super) fn flow_position(&self) -> Option<FlowPosition> {
+        self.level().and_then(|level| level.flow)
     }
 
-    pub(super) fn history(&self) -> &[u16] {
-        self.indent.history()
+    /// Moves to the given position within the innermost flow collection
+    pub(super) fn set_flow_position(&mut self, position: FlowPosition) {
+        self.indent.set_flow_position(position)
     }
 
-    pub(super) fn clamp(&mut self) {
-        self.indent.clamp()
+    pub(super) fn directives(&self) -> &Directives {
+        &self.directives
     }
 
-    pub(super) fn unclamp(&mut self) {
-        self.indent.unclamp()
+    /// Whether the current document's root node has started, a document
+    /// only has the one
+    pub(super) fn has_root(&self) -> bool {
+        self.root
+    }
+
+    /// Properties read ahead of the next node
+    pub(super) fn properties(&self) -> &Properties<'a> {
+        &self.properties
+    }
+
+    /// Takes the properties waiting for the next node, leaving none
+    pub(super) fn take_properties(&mut self) -> Properties<'a> {
+        take(&mut self.properties)
+    }
+
+    pub(super) fn set_properties(&mut self, properties: Properties<'a>) {
+        self.properties = properties
+    }
+
+    /// Builds a node starting at the given position, which takes any
+    /// properties waiting for it
+    pub(super) fn node(&mut self, kind: NodeKind<'a>, mark: Mark) -> Node<'a> {
+        let properties = take(&mut self.properties);
+        self.root = true;
+
+        Node {
+            anchor: properties.anchor,
+            tag: properties.tag,
+            ..kind.at(mark)
+        }
+    }
+
+    /// Marks whether the innermost open block collection is waiting
+    /// on the node of a key or entry. Flow collections are unaffected
+    pub(super) fn set_pending(&mut self, pending: bool) {
+        self.indent.set_pending(pending)
+    }
+
+    /// Marks whether the innermost open block mapping is waiting on the
+    /// value of a key given with '?'
+    pub(super) fn set_explicit(&mut self, explicit: bool) {
+        self.indent.set_explicit(explicit)
     }
 }
 
-impl<I> Scan<I, Active>
+impl<'a, I> Scan<'a, I, Active>
 where
     I: Iterator<Item = Byte>,
 {
@@ -75,32 +277,56 @@ where
         self.indent.update(new);
     }
 
-    pub(super) fn deactivate(self) -> Scan<I, Inactive> {
+    pub(super) fn deactivate(self) -> Scan<'a, I, Inactive> {
         Scan {
-            ch: self.ch,
+            window: self.window,
             iter: self.iter,
+            source: self.source,
+            mark: self.mark,
+            config: self.config,
             indent: self.indent.deactivate(),
+            properties: self.properties,
+            document: self.document,
+            documents: self.documents,
+            root: self.root,
+            directives: self.directives,
         }
     }
 }
 
-impl<I> Scan<I, Inactive>
+impl<'a, I> Scan<'a, I, Inactive>
 where
     I: Iterator<Item = Byte>,
 {
-    pub(super) fn new(iter: I) -> Self {
+    pub(super) fn new(iter: I, config: ParserConfig, source: Option<&'a [u8]>) -> Self {
         Self {
-            ch: None,
-            iter,
+            window: VecDeque::with_capacity(8),
+            iter: Decoder::new(iter),
+            source,
+            mark: Mark::default(),
+            config,
             indent: Default::default(),
+            properties: Default::default(),
+            document: 0,
+            documents: 0,
+            root: false,
+            directives: Default::default(),
         }
     }
 
-    pub(super) fn activate(self) -> Scan<I, Active> {
+    pub(super) fn activate(self) -> Scan<'a, I, Active> {
         Scan {
-            ch: self.ch,
+            window: self.window,
             iter: self.iter,
+            source: self.source,
+            mark: self.mark,
+            config: self.config,
             indent: self.indent.activate(),
+            properties: self.properties,
+            document: self.document,
+            documents: self.documents,
+            root: self.root,
+            directives: self.directives,
         }
     }
 }
@@ -108,16 +334,13 @@ where
 #[derive(Debug)]
 struct IndentTrack<S = Inactive> {
     //Flags
-    state: S,
-    clamp_floor: bool,
+    state: PhantomData<S>,
 
     // Values
-    floor: u16,
-    previous: u16,
     current: u16,
 
-    // Misc
-    history: Vec<u16>,
+    // Open block collections, innermost last
+    history: Vec<Level>,
 }
 
 impl<S> IndentTrack<S> {
@@ -125,43 +348,70 @@ impl<S> IndentTrack<S> {
         self.current
     }
 
-    fn previous(&self) -> u16 {
-        self.previous
+    fn history(&self) -> &[Level] {
+        self.history.as_ref()
+    }
+
+    fn open(&mut self, kind: LevelKind) {
+        self.set_pending(false);
+        self.history.push(Level {
+            indent: self.current,
+            kind,
+            pending: false,
+            explicit: false,
+            flow: None,
+        });
     }
 
-    fn floor(&self) -> u16 {
-        self.floor
+    fn open_flow(&mut self, kind: LevelKind) {
+        let position = match kind {
+            LevelKind::Mapping => FlowPosition::Key,
+            LevelKind::Sequence => FlowPosition::Value,
+        };
+
+        self.set_pending(false);
+        self.history.push(Level {
+            indent: self.current,
+            kind,
+            pending: false,
+            explicit: false,
+            flow: Some(position),
+        });
     }
 
-    fn history(&self) -> &[u16] {
-        self.history.as_ref()
+    fn close(&mut self) -> Option<Level> {
+        self.history.pop()
     }
 
-    fn clamp(&mut self) {
-        self.clamp_floor = true;
+    fn set_pending(&mut self, pending: bool) {
+        match self.history.last_mut() {
+            Some(level) if level.flow.is_none() => level.pending = pending,
+            _ => {}
+        }
     }
 
-    fn unclamp(&mut self) {
-        self.clamp_floor = false;
+    fn set_explicit(&mut self, explicit: bool) {
+        match self.history.last_mut() {
+            Some(level) if level.flow.is_none() => level.explicit = explicit,
+            _ => {}
+        }
+    }
+
+    fn set_flow_position(&mut self, position: FlowPosition) {
+        if let Some(level) = self.history.last_mut() {
+            level.flow = level.flow.and(Some(position));
+        }
     }
 }
 
 impl IndentTrack<Active> {
     fn update(&mut self, new: u16) {
-        if !self.clamp_floor && new > self.floor {
-            self.floor = new;
-        }
-        self.previous = self.current;
         self.current = new;
-        self.history.push(new);
     }
 
     fn deactivate(self) -> IndentTrack<Inactive> {
         IndentTrack {
-            state: Default::default(),
-            clamp_floor: self.clamp_floor,
-            floor: self.floor,
-            previous: self.previous,
+            state: PhantomData,
             current: self.current,
             history: self.history,
         }
@@ -171,10 +421,7 @@ impl IndentTrack<Active> {
 impl IndentTrack<Inactive> {
     fn activate(self) -> IndentTrack<Active> {
         IndentTrack {
-            state: Default::default(),
-            clamp_floor: self.clamp_floor,
-            floor: self.floor,
-            previous: self.previous,
+            state: PhantomData,
             current: self.current,
             history: self.history,
         }
@@ -184,16 +431,59 @@ impl IndentTrack<Inactive> {
 impl Default for IndentTrack {
     fn default() -> Self {
         Self {
-            state: Default::default(),
-            clamp_floor: true,
-            floor: 0,
-            previous: 0,
+            state: PhantomData,
             current: 0,
             history: Default::default(),
         }
     }
 }
 
+/// A node's properties, which precede its content
+#[derive(Debug, Default)]
+pub(super) struct Properties<'a> {
+    pub anchor: Option<Cow<'a, str>>,
+    // Resolved against the document's directives
+    pub tag: Option<Cow<'a, str>>,
+    // Line the properties were given on
+    pub line: usize,
+}
+
+impl Properties<'_> {
+    pub(super) fn is_empty(&self) -> bool {
+        self.anchor.is_none() && self.tag.is_none()
+    }
+}
+
+/// A collection opened at a given indentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Level {
+    pub indent: u16,
+    pub kind: LevelKind,
+    pub pending: bool,
+    // Set after a mapping's key given with '?', until its ':' is read
+    pub explicit: bool,
+    // Set for flow collections, which track their entries explicitly
+    pub flow: Option<FlowPosition>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum LevelKind {
+    Mapping,
+    Sequence,
+}
+
+/// What a flow collection expects next. Sequences alternate between
+/// Value and Separator, mappings cycle through all four
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum FlowPosition {
+    Key,
+    // A key was read, its ':' (if any) is next
+    Colon,
+    Value,
+    // An entry was read, a ',' or the closing bracket is next
+    Separator,
+}
+
 #[derive(Debug, Default)]
 pub(super) struct Active;
 
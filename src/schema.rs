@@ -0,0 +1,509 @@
+use std::convert::TryFrom;
+
+use super::error::{ErrorKind, Result};
+
+/// The prefix of the tags the schemas resolve to, which the
+/// secondary handle '!!' stands for by default
+const TAG_PREFIX: &str = "tag:yaml.org,2002:";
+
+/// What a plain scalar resolves to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Resolved {
+    Null,
+    Bool(bool),
+    Int(i64),
+    /// Only for integers too large for an i64
+    UInt(u64),
+    Float(f64),
+    Str,
+    /// The YAML 1.1 merge key, whose value's entries are merged into
+    /// the mapping holding it
+    Merge,
+}
+
+/// Decides what type the content of a plain scalar has. Quoted and
+/// block scalars are always strings
+pub trait Schema {
+    fn resolve(&self, value: &str) -> Resolved;
+}
+
+impl<S: Schema + ?Sized> Schema for &S {
+    fn resolve(&self, value: &str) -> Resolved {
+        (**self).resolve(value)
+    }
+}
+
+/// YAML 1.2 failsafe schema, every scalar is a string
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Failsafe;
+
+impl Schema for Failsafe {
+    fn resolve(&self, _: &str) -> Resolved {
+        Resolved::Str
+    }
+}
+
+/// YAML 1.2 JSON schema, only JSON's own literals are resolved.
+/// Anything else is a string
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Json;
+
+impl Schema for Json {
+    fn resolve(&self, value: &str) -> Resolved {
+        match value {
+            "null" => return Resolved::Null,
+            "true" => return Resolved::Bool(true),
+            "false" => return Resolved::Bool(false),
+            _ => {}
+        }
+
+        let unsigned = value.strip_prefix('-').unwrap_or(value);
+        let (mantissa, exponent) = split_exponent(unsigned);
+        let (int, fraction) = split_fraction(mantissa);
+
+        let valid = match int.as_bytes() {
+            [b'0'] => true,
+            [b'1'..=b'9', ..] => is_digits(int),
+            _ => false,
+        };
+
+        match (valid, fraction, exponent) {
+            (false, _, _) => Resolved::Str,
+            (true, None, None) => signed(value, 10).unwrap_or(Resolved::Str),
+            (true, fraction, exponent) => {
+                let valid = fraction.map_or(true, is_digits)
+                    && exponent.map_or(true, |e| is_number(strip_sign(e)));
+
+                float(value, valid)
+            }
+        }
+    }
+}
+
+/// YAML 1.2 core schema, the default
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Core;
+
+impl Schema for Core {
+    fn resolve(&self, value: &str) -> Resolved {
+        match value {
+            "" | "~" | "null" | "Null" | "NULL" => Resolved::Null,
+            "true" | "True" | "TRUE" => Resolved::Bool(true),
+            "false" | "False" | "FALSE" => Resolved::Bool(false),
+            _ => special_float(value)
+                .or_else(|| Self::int(value))
+                .or_else(|| Self::float(value))
+                .unwrap_or(Resolved::Str),
+        }
+    }
+}
+
+impl Core {
+    fn int(value: &str) -> Option<Resolved> {
+        match value.as_bytes() {
+            [b'0', b'x', ..] if is_radix(&value[2..], 16) => unsigned(&value[2..], 16),
+            [b'0', b'o', ..] if is_radix(&value[2..], 8) => unsigned(&value[2..], 8),
+            _ if is_number(strip_sign(value)) => signed(value, 10),
+            _ => None,
+        }
+    }
+
+    fn float(value: &str) -> Option<Resolved> {
+        let (mantissa, exponent) = split_exponent(strip_sign(value));
+        let (int, fraction) = split_fraction(mantissa);
+        let fraction = fraction.unwrap_or("");
+
+        let valid = is_digits(int)
+            && is_digits(fraction)
+            && !(int.is_empty() && fraction.is_empty())
+            && exponent.map_or(true, |e| is_number(strip_sign(e)));
+
+        match float(value, valid) {
+            Resolved::Str => None,
+            resolved => Some(resolved),
+        }
+    }
+}
+
+/// YAML 1.1 types, as still relied on by a lot of existing documents.
+/// Adds yes/no/on/off booleans, binary and leading zero octal integers,
+/// base 60 numbers and '_' digit separators
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Yaml11;
+
+impl Schema for Yaml11 {
+    fn resolve(&self, value: &str) -> Resolved {
+        match value {
+            "" | "~" | "null" | "Null" | "NULL" => Resolved::Null,
+            "<<" => Resolved::Merge,
+            "y" | "Y" | "yes" | "Yes" | "YES" | "true" | "True" | "TRUE" | "on" | "On" | "ON" => {
+                Resolved::Bool(true)
+            }
+            "n" | "N" | "no" | "No" | "NO" | "false" | "False" | "FALSE" | "off" | "Off"
+            | "OFF" => Resolved::Bool(false),
+            _ => special_float(value)
+                .or_else(|| Self::int(value))
+                .or_else(|| Self::float(value))
+                .unwrap_or(Resolved::Str),
+        }
+    }
+}
+
+impl Yaml11 {
+    fn int(value: &str) -> Option<Resolved> {
+        let negative = value.starts_with('-');
+        let unsigned = strip_sign(value);
+
+        let magnitude = match unsigned.as_bytes() {
+            [b'0', b'b', ..] => Self::digits(&unsigned[2..], 2)?,
+            [b'0', b'x', ..] => Self::digits(&unsigned[2..], 16)?,
+            [b'0'] => 0,
+            [b'0', ..] => Self::digits(&unsigned[1..], 8)?,
+            [b'1'..=b'9', ..] if unsigned.contains(':') => Self::sexagesimal(unsigned)?,
+            [b'1'..=b'9', ..] => Self::digits(unsigned, 10)?,
+            _ => return None,
+        };
+
+        let int = match negative {
+            true => -i128::from(magnitude),
+            false => i128::from(magnitude),
+        };
+
+        i64::try_from(int)
+            .map(Resolved::Int)
+            .or_else(|_| u64::try_from(int).map(Resolved::UInt))
+            .ok()
+    }
+
+    fn float(value: &str) -> Option<Resolved> {
+        let unsigned = strip_sign(value);
+        let (mantissa, exponent) = split_exponent(unsigned);
+        let (int, fraction) = split_fraction(mantissa);
+        let fraction = fraction?;
+
+        // The base 60 part of the number is the integer part
+        if int.contains(':') {
+            let fraction = fraction.replace('_', "");
+
+            if exponent.is_some() || !is_digits(&fraction) {
+                return None;
+            }
+
+            let magnitude =
+                Self::sexagesimal(int)? as f64 + format!("0.{}", fraction).parse::<f64>().ok()?;
+
+            return Some(Resolved::Float(match value.starts_with('-') {
+                true => -magnitude,
+                false => magnitude,
+            }));
+        }
+
+        let valid = (int.is_empty() || Self::is_separated(int, 10))
+            && (fraction.is_empty() || is_digits(&fraction.replace('_', "")))
+            && !(int.is_empty() && fraction.is_empty())
+            && exponent.map_or(true, |e| {
+                e.starts_with(['+', '-']) && is_number(strip_sign(e))
+            });
+
+        match float(&value.replace('_', ""), valid) {
+            Resolved::Str => None,
+            resolved => Some(resolved),
+        }
+    }
+
+    /// Digits of the given radix, possibly separated by '_'
+    fn digits(s: &str, radix: u32) -> Option<u64> {
+        match Self::is_separated(s, radix) {
+            true => u64::from_str_radix(&s.replace('_', ""), radix).ok(),
+            false => None,
+        }
+    }
+
+    fn is_separated(s: &str, radix: u32) -> bool {
+        s.chars().next().is_some_and(|c| c.is_digit(radix))
+            && s.chars().all(|c| c == '_' || c.is_digit(radix))
+    }
+
+    /// Base 60 numbers, such as times: 1:30:00. Every part
+    /// after the first must be less than 60
+    fn sexagesimal(s: &str) -> Option<u64> {
+        let mut parts = s.split(':');
+        let first = Self::digits(parts.next()?, 10)?;
+
+        parts.try_fold(first, |acc, part| {
+            let valid = (1..=2).contains(&part.len()) && is_digits(part);
+
+            match part.parse::<u64>() {
+                Ok(n) if valid && n < 60 => acc.checked_mul(60)?.checked_add(n),
+                _ => None,
+            }
+        })
+    }
+}
+
+/// Adds YAML 1.1 merge keys to another schema, resolving '<<' to
+/// [`Resolved::Merge`]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Merge<S>(pub S);
+
+impl<S: Schema> Schema for Merge<S> {
+    fn resolve(&self, value: &str) -> Resolved {
+        match value {
+            "<<" => Resolved::Merge,
+            _ => self.0.resolve(value),
+        }
+    }
+}
+
+/// Whether the tag is one resolved by the schemas themselves, rather
+/// than one left for the application. This includes the non-specific '!'
+pub(super) fn is_schema_tag(tag: &str) -> bool {
+    tag == "!"
+        || tag.strip_prefix(TAG_PREFIX).is_some_and(|name| {
+            matches!(
+                name,
+                "null" | "bool" | "int" | "float" | "str" | "seq" | "map"
+            )
+        })
+}
+
+/// Whether the tag is the merge key's own, `!!merge`
+pub(super) fn is_merge_tag(tag: &str) -> bool {
+    tag.strip_prefix(TAG_PREFIX) == Some("merge")
+}
+
+/// Resolves a scalar under the given tag. Without one of the schemas'
+/// tags, plain scalars are left to the schema and any other scalar is a
+/// string. With one, the scalar must hold a value of the tagged type
+pub(super) fn resolve_tagged<S>(
+    schema: &S,
+    tag: Option<&str>,
+    value: &str,
+    plain: bool,
+) -> Result<Resolved>
+where
+    S: Schema + ?Sized,
+{
+    let name = match tag {
+        Some("!") => return Ok(Resolved::Str),
+        Some(tag) if is_schema_tag(tag) => &tag[TAG_PREFIX.len()..],
+        _ if plain => return Ok(schema.resolve(value)),
+        _ => return Ok(Resolved::Str),
+    };
+
+    // Scalars the schema can't read are still given the core schema's types
+    let resolved = match schema.resolve(value) {
+        Resolved::Str | Resolved::Merge => Core.resolve(value),
+        resolved => resolved,
+    };
+
+    match (name, resolved) {
+        ("str", _) => Ok(Resolved::Str),
+        ("null", Resolved::Null) => Ok(Resolved::Null),
+        ("bool", Resolved::Bool(b)) => Ok(Resolved::Bool(b)),
+        ("int", Resolved::Int(i)) => Ok(Resolved::Int(i)),
+        ("int", Resolved::UInt(u)) => Ok(Resolved::UInt(u)),
+        ("float", Resolved::Float(f)) => Ok(Resolved::Float(f)),
+        ("float", Resolved::Int(i)) => Ok(Resolved::Float(i as f64)),
+        ("float", Resolved::UInt(u)) => Ok(Resolved::Float(u as f64)),
+        _ => {
+            let msg = format!("'{}' is not a valid !!{}", value, name);
+
+            Err(ErrorKind::InvalidTag.with_context(msg.as_str()))?
+        }
+    }
+}
+
+/// Checks a collection's tag, which may only be one of the schemas'
+/// if it names the collection's own kind
+pub(super) fn check_collection(tag: Option<&str>, mapping: bool) -> Result<()> {
+    let name = match tag {
+        Some(tag) if tag != "!" && is_schema_tag(tag) => &tag[TAG_PREFIX.len()..],
+        _ => return Ok(()),
+    };
+
+    match (name, mapping) {
+        ("map", true) | ("seq", false) => Ok(()),
+        _ => {
+            let msg = format!(
+                "a {} can't be a !!{}",
+                if mapping { "mapping" } else { "sequence" },
+                name
+            );
+
+            Err(ErrorKind::InvalidTag.with_context(msg.as_str()))?
+        }
+    }
+}
+
+/// Infinity and NaN, which the core and 1.1 schemas share
+fn special_float(value: &str) -> Option<Resolved> {
+    match value {
+        ".inf" | ".Inf" | ".INF" | "+.inf" | "+.Inf" | "+.INF" => {
+            Some(Resolved::Float(f64::INFINITY))
+        }
+        "-.inf" | "-.Inf" | "-.INF" => Some(Resolved::Float(f64::NEG_INFINITY)),
+        ".nan" | ".NaN" | ".NAN" => Some(Resolved::Float(f64::NAN)),
+        _ => None,
+    }
+}
+
+fn signed(value: &str, radix: u32) -> Option<Resolved> {
+    i64::from_str_radix(value, radix)
+        .map(Resolved::Int)
+        .or_else(|_| u64::from_str_radix(value, radix).map(Resolved::UInt))
+        .ok()
+}
+
+fn unsigned(digits: &str, radix: u32) -> Option<Resolved> {
+    u64::from_str_radix(digits, radix)
+        .ok()
+        .map(|n| i64::try_from(n).map_or(Resolved::UInt(n), Resolved::Int))
+}
+
+fn float(value: &str, valid: bool) -> Resolved {
+    match valid {
+        true => value.parse().map_or(Resolved::Str, Resolved::Float),
+        false => Resolved::Str,
+    }
+}
+
+fn split_exponent(s: &str) -> (&str, Option<&str>) {
+    match s.find(['e', 'E']) {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    }
+}
+
+fn split_fraction(s: &str) -> (&str, Option<&str>) {
+    match s.find('.') {
+        Some(i) => (&s[..i], Some(&s[i + 1..])),
+        None => (s, None),
+    }
+}
+
+fn is_radix(s: &str, radix: u32) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_digit(radix))
+}
+
+fn is_digits(s: &str) -> bool {
+    s.bytes().all(|b| b.is_ascii_digit())
+}
+
+fn is_number(s: &str) -> bool {
+    !s.is_empty() && is_digits(s)
+}
+
+fn strip_sign(s: &str) -> &str {
+    s.strip_prefix(['+', '-']).unwrap_or(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use Resolved::*;
+
+    fn check(schema: impl Schema, cases: &[(&str, Resolved)]) {
+        for (value, expected) in cases {
+            match (schema.resolve(value), expected) {
+                (Float(got), Float(expected)) if got.is_nan() && expected.is_nan() => {}
+                (got, expected) => assert_eq!(got, *expected, "{:?}", value),
+            }
+        }
+    }
+
+    #[test]
+    fn schema_failsafe() {
+        check(Failsafe, &[("", Str), ("null", Str), ("1", Str)]);
+    }
+
+    #[test]
+    fn schema_json() {
+        check(
+            Json,
+            &[
+                ("null", Null),
+                ("~", Str),
+                ("true", Bool(true)),
+                ("True", Str),
+                ("0", Int(0)),
+                ("-12", Int(-12)),
+                ("+12", Str),
+                ("012", Str),
+                ("1.5e3", Float(1500.0)),
+                ("-0.5", Float(-0.5)),
+                (".5", Str),
+                ("0x1F", Str),
+            ],
+        );
+    }
+
+    #[test]
+    fn schema_core() {
+        check(
+            Core,
+            &[
+                ("", Null),
+                ("~", Null),
+                ("NULL", Null),
+                ("True", Bool(true)),
+                ("yes", Str),
+                ("0x1F", Int(31)),
+                ("0o17", Int(15)),
+                ("0xFFFFFFFFFFFFFFFF", UInt(u64::MAX)),
+                ("+12", Int(12)),
+                ("012", Int(12)),
+                ("1e3", Float(1000.0)),
+                ("-.5", Float(-0.5)),
+                ("1.", Float(1.0)),
+                ("-.inf", Float(f64::NEG_INFINITY)),
+                (".NaN", Float(f64::NAN)),
+                ("1_000", Str),
+                ("1:30", Str),
+                ("0x", Str),
+                (".", Str),
+                ("1e", Str),
+                ("infinity", Str),
+            ],
+        );
+    }
+
+    #[test]
+    fn schema_yaml11() {
+        check(
+            Yaml11,
+            &[
+                ("~", Null),
+                ("yes", Bool(true)),
+                ("Off", Bool(false)),
+                ("n", Bool(false)),
+                ("0b1010", Int(10)),
+                ("017", Int(15)),
+                ("-0x1_F", Int(-31)),
+                ("1_000", Int(1000)),
+                ("190:20:30", Int(685_230)),
+                ("-1:30", Int(-90)),
+                ("1:60", Str),
+                ("1.5", Float(1.5)),
+                ("1_0.2_5", Float(10.25)),
+                ("6.8523015e+5", Float(685_230.15)),
+                ("1e3", Str),
+                ("190:20:30.15", Float(685_230.15)),
+                (".inf", Float(f64::INFINITY)),
+                ("08", Str),
+                ("0o17", Str),
+                ("<<", Resolved::Merge),
+            ],
+        );
+    }
+
+    #[test]
+    fn schema_merge() {
+        check(
+            super::Merge(Core),
+            &[("<<", Resolved::Merge), ("<", Str), ("null", Null)],
+        );
+        check(Core, &[("<<", Str)]);
+    }
+}
@@ -0,0 +1,93 @@
+use {
+    super::error::{ErrorKind, Result},
+    std::{borrow::Cow, ops::Range, str},
+};
+
+/// A scalar's content as it is scanned. Content read verbatim from a
+/// borrowable input is only tracked as a span of the input, anything added
+/// after the span ends is copied
+#[derive(Debug, Default)]
+pub(super) struct Scratch {
+    // Span of the input the content starts with
+    span: Range<usize>,
+    // Content following the span
+    bytes: Vec<u8>,
+    // Number of trailing whitespace bytes read from the input
+    trailing: usize,
+}
+
+impl Scratch {
+    /// Adds a byte read from the given index of the input, extending the
+    /// span if the input is borrowable and the content still verbatim
+    pub(super) fn extend_from(&mut self, ch: u8, index: usize, borrow: bool) {
+        let verbatim = self.bytes.is_empty() && (self.span.is_empty() || self.span.end == index);
+
+        match (borrow, verbatim) {
+            (true, true) if self.span.is_empty() => self.span = index..index + 1,
+            (true, true) => self.span.end += 1,
+            _ => self.bytes.push(ch),
+        }
+
+        match ch {
+            b' ' | b'\t' => self.trailing += 1,
+            _ => self.trailing = 0,
+        }
+    }
+
+    /// Adds a byte which isn't part of the input, such as an unescaped
+    /// character or folded line break
+    pub(super) fn push(&mut self, ch: u8) {
+        self.bytes.push(ch);
+        self.trailing = 0;
+    }
+
+    pub(super) fn extend(&mut self, bytes: impl IntoIterator<Item = u8>) {
+        self.bytes.extend(bytes);
+        self.trailing = 0;
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.span.len() + self.bytes.len()
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.span.is_empty() && self.bytes.is_empty()
+    }
+
+    /// Whether the content ends with whitespace read from the input
+    pub(super) fn ends_with_whitespace(&self) -> bool {
+        self.trailing > 0
+    }
+
+    /// Removes trailing whitespace read from the input
+    pub(super) fn trim_end(&mut self) {
+        let bytes = self.trailing.min(self.bytes.len());
+
+        self.bytes.truncate(self.bytes.len() - bytes);
+        self.span.end -= self.trailing - bytes;
+        self.trailing = 0;
+    }
+
+    /// Resolves the content against the (borrowable) input it was read
+    /// from, only copying if the content isn't a single span of it
+    pub(super) fn into_str<'a>(self, source: Option<&'a [u8]>) -> Result<Cow<'a, str>> {
+        let head = match source {
+            Some(source) if !self.span.is_empty() => {
+                source.get(self.span).ok_or(ErrorKind::StateViolation)?
+            }
+            _ => &[],
+        };
+
+        let content = if self.bytes.is_empty() {
+            str::from_utf8(head).map(Cow::Borrowed).ok()
+        } else if head.is_empty() {
+            String::from_utf8(self.bytes).map(Cow::Owned).ok()
+        } else {
+            String::from_utf8([head, &self.bytes].concat())
+                .map(Cow::Owned)
+                .ok()
+        };
+
+        Ok(content.ok_or_else(|| ErrorKind::ScalarInvalid.with_context("invalid UTF-8"))?)
+    }
+}
@@ -0,0 +1,511 @@
+use std::{fmt::Display, io};
+
+use serde::ser::{self, Serialize};
+
+use super::{
+    emitter::Emitter,
+    error::{Error, ErrorKind, Result},
+    mark::Mark,
+    node::{NodeKind, ScalarStyle},
+    schema::{Core, Resolved, Schema, Yaml11},
+};
+
+/// Serialize the given value as a string of YAML
+pub fn to_string<T>(value: &T) -> Result<String>
+where
+    T: ?Sized + Serialize,
+{
+    let mut buffer = Vec::new();
+    to_writer(&mut buffer, value)?;
+
+    String::from_utf8(buffer).map_err(|_| {
+        ErrorKind::StateViolation
+            .with_context("emitted invalid UTF-8")
+            .into()
+    })
+}
+
+/// Serialize the given value as YAML into the given stream
+pub fn to_writer<W, T>(writer: W, value: &T) -> Result<()>
+where
+    W: io::Write,
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer {
+        emitter: Emitter::new(writer),
+    };
+
+    serializer.emit(NodeKind::StreamStart)?;
+    serializer.emit(NodeKind::DocumentStart(Default::default()))?;
+    value.serialize(&mut serializer)?;
+    serializer.emit(NodeKind::DocumentEnd)?;
+    serializer.emit(NodeKind::StreamEnd)
+}
+
+/// Turns serde's data model into nodes, feeding them to the emitter
+struct Serializer<W> {
+    emitter: Emitter<W>,
+}
+
+impl<W> Serializer<W>
+where
+    W: io::Write,
+{
+    fn emit(&mut self, node: NodeKind) -> Result<()> {
+        self.emitter.emit(&node.at(Mark::default()))
+    }
+
+    fn plain(&mut self, value: &str) -> Result<()> {
+        self.emit(NodeKind::scalar(value, ScalarStyle::Plain))
+    }
+
+    fn float(&mut self, value: f64, repr: String) -> Result<()> {
+        if value.is_nan() {
+            self.plain(".nan")
+        } else if value.is_infinite() {
+            self.plain(if value > 0.0 { ".inf" } else { "-.inf" })
+        } else if repr.contains('.') {
+            self.plain(&repr)
+        } else {
+            // Otherwise it would be read back as an integer
+            self.plain(&format!("{}.0", repr))
+        }
+    }
+
+    /// Starts a mapping of the given variant's name to its content
+    fn variant(&mut self, variant: &str) -> Result<()> {
+        self.emit(NodeKind::MappingStart)?;
+        self.plain(variant)
+    }
+}
+
+impl<W> ser::Serializer for &mut Serializer<W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.plain(if v { "true" } else { "false" })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.plain(&v.to_string())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        self.plain(&v.to_string())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.plain(&v.to_string())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        self.plain(&v.to_string())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.float(v.into(), v.to_string())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.float(v, v.to_string())
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Strings which would be read back as another type are quoted,
+    /// under either the core or the 1.1 schema
+    fn serialize_str(self, v: &str) -> Result<()> {
+        let style = match (Core.resolve(v), Yaml11.resolve(v)) {
+            (Resolved::Str, Resolved::Str) => ScalarStyle::Plain,
+            _ => ScalarStyle::SingleQuoted,
+        };
+
+        self.emit(NodeKind::scalar(v, style))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        ser::Serializer::collect_seq(self, v)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        self.plain("null")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<()> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.variant(variant)?;
+        value.serialize(&mut *self)?;
+        self.emit(NodeKind::MappingEnd)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self> {
+        self.emit(NodeKind::SequenceStart)?;
+
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self> {
+        self.variant(variant)?;
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self> {
+        self.emit(NodeKind::MappingStart)?;
+
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self> {
+        self.variant(variant)?;
+        self.serialize_map(Some(len))
+    }
+}
+
+impl<W> ser::SerializeSeq for &mut Serializer<W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.emit(NodeKind::SequenceEnd)
+    }
+}
+
+impl<W> ser::SerializeTuple for &mut Serializer<W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.emit(NodeKind::SequenceEnd)
+    }
+}
+
+impl<W> ser::SerializeTupleStruct for &mut Serializer<W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.emit(NodeKind::SequenceEnd)
+    }
+}
+
+impl<W> ser::SerializeTupleVariant for &mut Serializer<W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.emit(NodeKind::SequenceEnd)?;
+        self.emit(NodeKind::MappingEnd)
+    }
+}
+
+impl<W> ser::SerializeMap for &mut Serializer<W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.emit(NodeKind::MappingEnd)
+    }
+}
+
+impl<W> ser::SerializeStruct for &mut Serializer<W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.plain(key)?;
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.emit(NodeKind::MappingEnd)
+    }
+}
+
+impl<W> ser::SerializeStructVariant for &mut Serializer<W>
+where
+    W: io::Write,
+{
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.plain(key)?;
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        self.emit(NodeKind::MappingEnd)?;
+        self.emit(NodeKind::MappingEnd)
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        ErrorKind::Message(msg.to_string().into()).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::from_str;
+
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        version: String,
+        ratio: f64,
+        tags: Vec<String>,
+        owner: Option<String>,
+        limits: BTreeMap<String, u32>,
+        modes: Vec<Mode>,
+        empty: Vec<u8>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Mode {
+        Fast,
+        Custom(u8),
+        Range(u8, u8),
+        Named { label: String },
+    }
+
+    fn config() -> Config {
+        Config {
+            name: "oxiyaml: a parser".into(),
+            version: "1.0".into(),
+            ratio: 2.0,
+            tags: vec!["null".into(), "yes".into(), "#tag".into(), "plain".into()],
+            owner: None,
+            limits: vec![("depth".into(), 16)].into_iter().collect(),
+            modes: vec![
+                Mode::Fast,
+                Mode::Custom(7),
+                Mode::Range(1, 2),
+                Mode::Named { label: "x".into() },
+            ],
+            empty: vec![],
+        }
+    }
+
+    #[test]
+    fn struct_nested() -> Result<()> {
+        assert_eq!(
+            to_string(&config())?,
+            "name: 'oxiyaml: a parser'
+version: '1.0'
+ratio: 2.0
+tags:
+  - 'null'
+  - 'yes'
+  - '#tag'
+  - plain
+owner: null
+limits:
+  depth: 16
+modes:
+  - Fast
+  - Custom: 7
+  - Range:
+      - 1
+      - 2
+  - Named:
+      label: x
+empty: []
+"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn struct_roundtrip() -> Result<()> {
+        let text = to_string(&config())?;
+
+        assert_eq!(from_str::<Config>(&text)?, config());
+
+        Ok(())
+    }
+
+    #[test]
+    fn scalar_floats() -> Result<()> {
+        let floats = vec![0.5, -1.0, 1e20, f64::INFINITY];
+        let text = to_string(&floats)?;
+
+        assert_eq!(text, "- 0.5\n- -1.0\n- 100000000000000000000.0\n- .inf\n");
+        assert_eq!(from_str::<Vec<f64>>(&text)?, floats);
+        assert!(from_str::<f64>(&to_string(&f64::NAN)?)?.is_nan());
+
+        Ok(())
+    }
+}
@@ -1,6 +1,11 @@
+use std::{char, iter::repeat, str};
+
 use super::{
-    error::{Error, ErrorKind, Result},
+    error::{Error, ErrorKind, Feature, Result},
+    mark::Mark,
+    node::{Directives, Node, NodeKind, ScalarStyle, Version},
     scanner::*,
+    scratch::Scratch,
 };
 
 macro_rules! make_local {
@@ -17,26 +22,48 @@ macro_rules! make_local {
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(super) enum Marker {
     // Base
-    Start,
     //WhiteSpace,
     //Ignore,
     LineEnd,
     LineStart,
+    DocumentStart,
+    DocumentEnd,
+    StreamEnd,
     Done,
 
     // Ambiguous
     AmbiguousScalar,
     AmbiguousColon,
 
+    // Node
+    NodeProperties,
+    Alias,
+    EmptyNode,
+
     // Map
     MapStart,
     MapVerifyKey,
     MapWhiteSpace,
     MapValue,
     MapEnd,
+    MapExplicitKey,
+    MapExplicitValue,
+
+    // Sequence
+    SequenceStart,
+    SequenceEntry,
+    SequenceEnd,
+
+    // Flow
+    FlowStart,
+    FlowEntry,
+    FlowScalar,
+    FlowEnd,
 
     // Scalar
     ScalarLiteral,
+    ScalarQuoted,
+    ScalarBlock,
 }
 
 /* Base */
@@ -46,15 +73,158 @@ pub(super) struct Start;
 impl Start {
     pub(super) fn find_next(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
         match iter.peak()? {
-            Some(_) => Ok(Marker::LineStart),
-            None => Ok(Marker::Done),
+            Some(_) => Ok(Marker::DocumentStart),
+            None => Ok(Marker::StreamEnd),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct DocumentStart {
+    pub comment: Option<Node<'static>>,
+    pub warning: Option<Error>,
+    pub directives: Directives,
+    // Where the document's '---' marker starts, if it has one
+    pub mark: Option<Mark>,
+    // Set if the document was started explicitly but holds no node
+    pub empty: bool,
+}
+
+impl DocumentStart {
+    pub(super) fn find_next(
+        &self,
+        iter: &mut Scan<impl Iterator<Item = Byte>, Active>,
+    ) -> Result<Marker> {
+        match iter.peak()? {
+            _ if self.comment.is_some() || self.warning.is_some() || self.empty => {
+                Ok(Marker::DocumentStart)
+            }
+            Some(_) => find_line_node(iter),
+            None => Ok(Marker::StreamEnd),
+        }
+    }
+
+    /// Skips any blank, comment or directive lines preceding the
+    /// document's first node, along with its '---' marker, returning true
+    /// if the document has any content. Stops early at a comment or
+    /// warning which should be emitted
+    pub(super) fn skip_blank(
+        &mut self,
+        iter: &mut Scan<impl Iterator<Item = Byte>, Active>,
+    ) -> Result<bool> {
+        loop {
+            let marker = match document_marker(iter)? {
+                // The document ends before reaching a node
+                Some(_) if self.mark.is_some() => {
+                    self.empty = true;
+                    break Ok(true);
+                }
+                Some(b'-') => {
+                    self.mark = Some(iter.mark());
+                    skip_marker(iter);
+                    true
+                }
+                // An end marker outside of a document is allowed, if useless
+                Some(_) => {
+                    self.require_marker()?;
+                    skip_marker(iter);
+                    true
+                }
+                None => false,
+            };
+
+            let indent = match marker {
+                true => skip_separation(iter)?,
+                false => count_indent(iter)?,
+            };
+
+            match iter.peak()? {
+                Some(b'\n') | Some(b'\r') => line_break(iter)?,
+                Some(b'#') => {
+                    self.comment = parse_comment(iter)?;
+
+                    if self.comment.is_some() {
+                        break Ok(false);
+                    }
+                }
+                Some(b'%') if indent == 0 && self.mark.is_none() => {
+                    self.warning = parse_directive(iter, &mut self.directives)?;
+
+                    if self.warning.is_some() {
+                        break Ok(false);
+                    }
+                }
+                Some(_) => {
+                    self.require_marker()?;
+                    iter.update_indent(indent);
+                    break Ok(true);
+                }
+                None if self.mark.is_some() => {
+                    self.empty = true;
+                    break Ok(true);
+                }
+                None => {
+                    self.require_marker()?;
+                    break Ok(false);
+                }
+            }
+        }
+    }
+
+    /// Checks that any directives are followed by a '---' marker
+    fn require_marker(&self) -> Result<()> {
+        match self.mark {
+            None if !self.directives.is_empty() => Err(ErrorKind::InvalidDirective
+                .with_context("directives must be followed by a '---' marker"))?,
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct DocumentEnd;
+
+impl DocumentEnd {
+    pub(super) fn find_next(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
+        match iter.peak()? {
+            Some(_) => Ok(Marker::DocumentStart),
+            None => Ok(Marker::StreamEnd),
+        }
+    }
+
+    /// Consumes the '...' marker ending the document, if it has one. Only
+    /// a comment may follow the marker on its line
+    pub(super) fn close(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<()> {
+        if document_marker(iter)? != Some(b'.') {
+            return Ok(());
+        }
+
+        skip_marker(iter);
+
+        while let Some(b' ') | Some(b'\t') = iter.peak()? {
+            iter.discard()
+        }
+
+        match iter.peak()? {
+            Some(b'\n') | Some(b'\r') | Some(b'#') | None => Ok(()),
+            Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'\n', b'\r'], err)))?,
         }
     }
 }
 
+#[derive(Debug, Default)]
+pub(super) struct StreamEnd;
+
+impl StreamEnd {
+    pub(super) fn find_next(&self, _: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
+        Ok(Marker::Done)
+    }
+}
+
 #[derive(Debug, Default)]
 pub(super) struct AmbiguousScalar {
-    scratch: Vec<u8>,
+    scratch: Scratch,
+    start: Option<Mark>,
 }
 
 impl AmbiguousScalar {
@@ -62,17 +232,22 @@ impl AmbiguousScalar {
         &mut self,
         iter: &mut Scan<impl Iterator<Item = Byte>>,
     ) -> Result<Marker> {
-        make_local!(iter);
+        self.start.get_or_insert(iter.mark());
+
+        if self.scratch.is_empty() {
+            plain_first(iter)?;
+        }
 
         loop {
             match iter.peak()? {
-                Some(ch @ b' ')
-                | Some(ch @ b'\t')
-                | Some(ch @ b'a'..=b'z')
-                | Some(ch @ b'A'..=b'Z') => discard_and!(self.scratch.push(ch)),
                 Some(b':') => break Ok(Marker::AmbiguousColon),
-                Some(err) => Err(ErrorKind::InvalidChar.with_context(err))?,
-                None => break Ok(Marker::ScalarLiteral),
+                // Only a '#' preceded by whitespace starts a comment
+                Some(b'#') if self.scratch.ends_with_whitespace() => {
+                    break Ok(Marker::ScalarLiteral)
+                }
+                Some(b'\n') | Some(b'\r') | None => break Ok(Marker::ScalarLiteral),
+                Some(err) if !is_printable(err) => Err(ErrorKind::InvalidChar.with_context(err))?,
+                Some(_) => iter.take(&mut self.scratch)?,
             }
         }
     }
@@ -82,13 +257,15 @@ impl From<AmbiguousScalar> for AmbiguousColon {
     fn from(prev: AmbiguousScalar) -> Self {
         Self {
             scratch: prev.scratch,
+            mark: prev.start.unwrap_or_default(),
         }
     }
 }
 
 #[derive(Debug)]
 pub(super) struct AmbiguousColon {
-    pub scratch: Vec<u8>,
+    pub scratch: Scratch,
+    pub mark: Mark,
 }
 
 impl AmbiguousColon {
@@ -99,11 +276,19 @@ impl AmbiguousColon {
         make_local!(iter);
 
         match iter.peak()? {
+            // A ':' not followed by whitespace is part of the scalar
+            Some(b':')
+                if iter
+                    .peek_n(1)?
+                    .is_some_and(|next| is_plain_safe(next, false)) =>
+            {
+                iter.take(&mut self.scratch)?;
+                Ok(Marker::AmbiguousScalar)
+            }
             Some(b':') => discard_and!(match iter.peak()? {
                 Some(b' ') | Some(b'\t') => discard_and!(Ok(Marker::MapStart)),
-                Some(b'a'..=b'z') | Some(b'A'..=b'Z') => Ok(Marker::AmbiguousScalar),
+                Some(b'\n') | Some(b'\r') | None => Ok(Marker::MapStart),
                 Some(err) => Err(ErrorKind::InvalidChar.with_context(err))?,
-                None => Err(ErrorKind::InvalidEOF)?,
             }),
             Some(err) => Err(ErrorKind::InvalidChar.with_context(([b':'], err)))?,
             None => Err(ErrorKind::InvalidEOF)?,
@@ -115,6 +300,7 @@ impl From<AmbiguousColon> for AmbiguousScalar {
     fn from(prev: AmbiguousColon) -> Self {
         Self {
             scratch: prev.scratch,
+            start: Some(prev.mark),
         }
     }
 }
@@ -128,9 +314,8 @@ impl LineStart {
         iter: &mut Scan<impl Iterator<Item = Byte>, Active>,
     ) -> Result<Marker> {
         match iter.peak()? {
-            Some(b'\n') | Some(b'\r') => Ok(Marker::LineEnd),
-            Some(_) => Ok(Marker::AmbiguousScalar),
-            None => Ok(Marker::Done),
+            Some(b'\n') | Some(b'\r') | Some(b'#') => Ok(Marker::LineEnd),
+            _ => find_line_node(iter),
         }
     }
 
@@ -138,52 +323,41 @@ impl LineStart {
         &self,
         iter: &mut Scan<impl Iterator<Item = Byte>, Active>,
     ) -> Result<()> {
-        let indent = self.count_indent(iter)?;
+        let indent = count_indent(iter)?;
         iter.update_indent(indent);
 
         Ok(())
     }
-
-    fn count_indent(&self, iter: &mut Scan<impl Iterator<Item = Byte>, Active>) -> Result<u16> {
-        make_local!(iter);
-        let mut count = 0;
-        loop {
-            match iter.peak()? {
-                Some(b' ') | Some(b'\t') => discard_and!(count += 1),
-                _ => break,
-            }
-        }
-
-        Ok(count)
-    }
 }
 
 #[derive(Debug, Default)]
-pub(super) struct LineEnd;
+pub(super) struct LineEnd {
+    pub comment: Option<Node<'static>>,
+}
 
 impl LineEnd {
     pub(super) fn find_next(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
         match iter.peak()? {
-            Some(b' ') | Some(b'\t') | Some(b'a'..=b'z') | Some(b'A'..=b'Z') => {
-                Ok(Marker::LineStart)
-            }
-            None => Ok(Marker::Done),
-            Some(err) => Err(ErrorKind::InvalidChar.with_context(err))?,
+            Some(_) => Ok(Marker::LineStart),
+            None => Ok(stream_end(iter)),
         }
     }
 
-    pub(super) fn close_line(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<()> {
-        make_local!(iter);
-        loop {
-            match iter.peak()? {
-                Some(b'\n') => discard_and!(break Ok(())),
-                Some(b'\r') => discard_and!(match iter.peak()? {
-                    Some(b'\n') => discard_and!(break Ok(())),
-                    _ => break Err(ErrorKind::SoloCarriageReturn)?,
-                }),
-                None => break Err(ErrorKind::InvalidEOL.with_context(b'\n'))?,
-                _ => iter.discard(),
-            }
+    /// Consumes the rest of the line, which may only hold whitespace and
+    /// a comment
+    pub(super) fn close_line(&mut self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<()> {
+        while let Some(b' ') | Some(b'\t') = iter.peak()? {
+            iter.discard()
+        }
+
+        if let Some(b'#') = iter.peak()? {
+            self.comment = parse_comment(iter)?;
+        }
+
+        match iter.peak()? {
+            Some(b'\n') | Some(b'\r') => line_break(iter),
+            Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'\n', b'\r'], err)))?,
+            None => Ok(()),
         }
     }
 }
@@ -208,183 +382,1965 @@ impl From<Error> for Failure {
     }
 }
 
-/* Scalar */
+/* Node */
 
 #[derive(Debug, Default)]
-pub(super) struct ScalarLiteral {
-    pub scalar: Vec<u8>,
+pub(super) struct NodeProperties {
+    // Set if the properties belong to a mapping's value, which can't be a key
+    value: bool,
 }
 
-impl ScalarLiteral {
+impl NodeProperties {
+    /// Finds the node following the properties, which may start on a
+    /// later line
     pub(super) fn find_next(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
         match iter.peak()? {
-            Some(_) => unimplemented!("Can't parse after scalar literal"),
-            None => Ok(Marker::Done),
+            // A key must be on the line of its ':'
+            Some(b'\n') | Some(b'\r') | Some(b'#') | None if is_key_position(iter) => {
+                Err(missing_value_indicator())
+            }
+            Some(b'\n') | Some(b'\r') | Some(b'#') => Ok(Marker::LineEnd),
+            Some(b'\'') | Some(b'"') => Ok(Marker::ScalarQuoted),
+            Some(b'|') | Some(b'>') => Ok(Marker::ScalarBlock),
+            Some(b'[') | Some(b'{') => Ok(Marker::FlowStart),
+            Some(b'*') => {
+                Err(ErrorKind::InvalidChar.with_context("an alias can't have properties"))?
+            }
+            Some(_) if self.value => Ok(Marker::MapValue),
+            Some(_) => Ok(Marker::AmbiguousScalar),
+            None => Ok(Marker::EmptyNode),
         }
     }
+
+    pub(super) fn parse_properties(
+        &self,
+        iter: &mut Scan<impl Iterator<Item = Byte>>,
+    ) -> Result<()> {
+        parse_properties(iter)
+    }
 }
 
-impl From<AmbiguousScalar> for ScalarLiteral {
-    fn from(prev: AmbiguousScalar) -> Self {
-        Self {
-            scalar: prev.scratch,
+/// Stands an empty scalar in for the missing node of a block collection's
+/// pending key or entry, or for the value of a key given with '?'
+#[derive(Debug, Default)]
+pub(super) struct EmptyNode;
+
+impl EmptyNode {
+    /// Marks the missing node as found
+    pub(super) fn resolve(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) {
+        match iter.level() {
+            Some(level) if level.pending => iter.set_pending(false),
+            _ => iter.set_explicit(false),
         }
     }
+
+    /// Finds what follows the empty node, which always stands either
+    /// after a line's indentation or at the end of the stream
+    pub(super) fn find_next(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
+        find_line_node(iter)
+    }
 }
 
-/* Map */
-#[derive(Debug)]
-pub(super) struct MapStart {
-    indent_floor: u16,
-    scratch: Vec<u8>,
+impl From<MapWhiteSpace> for NodeProperties {
+    fn from(_: MapWhiteSpace) -> Self {
+        Self { value: true }
+    }
 }
 
-impl MapStart {
+#[derive(Debug, Default)]
+pub(super) struct Alias {
+    pub name: Scratch,
+    pub mark: Mark,
+    // Set if the alias is a mapping's value, and thus can't be a key
+    value: bool,
+    // Set if the alias stands where its mapping expects a key
+    key: bool,
+}
+
+impl Alias {
+    /// Finds what follows an alias in the block context, which may
+    /// be a mapping key
     pub(super) fn find_next(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
-        match iter.peak()? {
-            Some(_) => Ok(Marker::MapVerifyKey),
-            None => Err(ErrorKind::EOFMapping.into()),
+        make_local!(iter);
+
+        while let Some(b' ') | Some(b'\t') = iter.peak()? {
+            iter.discard()
         }
-    }
 
-    pub(super) fn new(current_floor: u16) -> Self {
-        Self {
-            indent_floor: current_floor,
-            scratch: Default::default(),
+        if self.key && iter.peak()? != Some(b':') {
+            return Err(missing_value_indicator());
         }
-    }
 
-    pub(super) fn extend_from(current_floor: u16, buffer: Vec<u8>) -> Self {
-        Self {
-            indent_floor: current_floor,
-            scratch: buffer,
+        match iter.peak()? {
+            Some(b'\n') | Some(b'\r') | Some(b'#') => Ok(Marker::LineEnd),
+            Some(b':') if !self.value => discard_and!(match iter.peak()? {
+                Some(b' ') | Some(b'\t') => discard_and!(Ok(Marker::MapStart)),
+                Some(b'\n') | Some(b'\r') | None => Ok(Marker::MapStart),
+                Some(err) => Err(ErrorKind::InvalidChar.with_context(err))?,
+            }),
+            Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'\n', b'\r'], err)))?,
+            None => Ok(stream_end(iter)),
         }
     }
+
+    pub(super) fn parse_alias(
+        &mut self,
+        iter: &mut Scan<impl Iterator<Item = Byte>>,
+    ) -> Result<()> {
+        self.mark = iter.mark();
+        self.key = is_key_position(iter);
+        self.name = parse_alias(iter)?;
+
+        Ok(())
+    }
 }
 
-impl From<MapStart> for MapVerifyKey {
-    fn from(prev: MapStart) -> Self {
+impl From<MapWhiteSpace> for Alias {
+    fn from(_: MapWhiteSpace) -> Self {
         Self {
-            indent_floor: prev.indent_floor,
-            key: prev.scratch,
+            value: true,
+            ..Default::default()
         }
     }
 }
 
-#[derive(Debug)]
-pub(super) struct MapVerifyKey {
-    indent_floor: u16,
-    pub key: Vec<u8>,
+/* Scalar */
+
+#[derive(Debug, Default)]
+pub(super) struct ScalarLiteral {
+    pub scalar: Scratch,
+    pub mark: Mark,
+    // Set if the scalar's final line break was consumed
+    folded: bool,
 }
 
-impl MapVerifyKey {
+impl ScalarLiteral {
     pub(super) fn find_next(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
-        match iter.peak()? {
-            Some(_) => Ok(Marker::MapWhiteSpace),
-            None => Err(ErrorKind::EOFMapping)?,
-        }
+        plain_next(iter, self.folded)
     }
 
-    pub(super) fn parse_key(&mut self, _: &mut Scan<impl Iterator<Item = Byte>>) -> Result<()> {
-        for ch in self.key.iter() {
-            match ch {
-                b'a'..=b'z' | b'A'..=b'Z' | b' ' | b'\t' => (),
-                err => return Err(ErrorKind::InvalidChar.with_context(*err))?,
-            }
+    /// Folds any continuation lines into the scalar
+    pub(super) fn parse_scalar(
+        &mut self,
+        iter: &mut Scan<impl Iterator<Item = Byte>>,
+    ) -> Result<()> {
+        // Only a key may stand where its mapping expects one
+        if is_key_position(iter) {
+            return Err(missing_value_indicator());
         }
 
-        while !self.key.is_empty() && is_whitespace(self.key.last().unwrap()) {
-            dbg!(self.key.pop());
-        }
+        self.folded = plain_fold(iter, &mut self.scalar)?;
 
         Ok(())
     }
 }
 
-impl From<MapVerifyKey> for MapWhiteSpace {
-    fn from(prev: MapVerifyKey) -> Self {
-        Self {
-            indent_floor: prev.indent_floor,
-        }
-    }
-}
-
-#[derive(Debug)]
-pub(super) struct MapWhiteSpace {
-    indent_floor: u16,
+#[derive(Debug, Default)]
+pub(super) struct ScalarQuoted {
+    pub scalar: Scratch,
+    pub mark: Mark,
+    double: bool,
+    multiline: bool,
+    // Set if the scalar is a mapping's value, and thus can't be a key
+    value: bool,
+    // Set if the scalar stands where its mapping expects a key
+    key: bool,
 }
 
-impl MapWhiteSpace {
+impl ScalarQuoted {
     pub(super) fn find_next(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
+        make_local!(iter);
+
+        if iter.flow_position().is_some() {
+            return Ok(Marker::FlowEntry);
+        }
+
+        let mut separated = false;
+
+        while let Some(b' ') | Some(b'\t') = iter.peak()? {
+            discard_and!(separated = true)
+        }
+
+        if self.key && iter.peak()? != Some(b':') {
+            return Err(missing_value_indicator());
+        }
+
         match iter.peak()? {
-            Some(b'a'..=b'z') | Some(b'A'..=b'Z') => Ok(Marker::MapValue),
-            _ => Err(ErrorKind::Message("Unclear fail state".into()))?,
+            Some(b'#') if separated => Ok(Marker::LineEnd),
+            Some(b':') if !self.value && !self.multiline => discard_and!(match iter.peak()? {
+                Some(b' ') | Some(b'\t') => discard_and!(Ok(Marker::MapStart)),
+                Some(b'\n') | Some(b'\r') | None => Ok(Marker::MapStart),
+                Some(err) => Err(ErrorKind::InvalidChar.with_context(err))?,
+            }),
+            Some(b'\n') | Some(b'\r') => Ok(Marker::LineEnd),
+            Some(err) => Err(ErrorKind::InvalidChar.with_context(err))?,
+            None => Ok(stream_end(iter)),
         }
     }
 
-    pub(super) fn parse_whitespace(
-        &self,
+    pub(super) fn style(&self) -> ScalarStyle {
+        if self.double {
+            ScalarStyle::DoubleQuoted
+        } else {
+            ScalarStyle::SingleQuoted
+        }
+    }
+
+    /// Parses a quoted scalar, from its opening to closing quote
+    pub(super) fn parse_scalar(
+        &mut self,
         iter: &mut Scan<impl Iterator<Item = Byte>>,
     ) -> Result<()> {
+        make_local!(iter);
+        self.mark = iter.mark();
+        self.key = is_key_position(iter);
+
+        match iter.peak()? {
+            Some(b'"') => discard_and!(self.double = true),
+            Some(b'\'') => discard_and!(self.double = false),
+            Some(err) => Err(ErrorKind::InvalidChar.with_context((b"'\"", err)))?,
+            None => Err(ErrorKind::InvalidEOF)?,
+        }
+
         loop {
             match iter.peak()? {
-                Some(b' ') | Some(b'\t') => iter.discard(),
-                Some(b'a'..=b'z') | Some(b'A'..=b'Z') => break Ok(()),
-                Some(b'|') | Some(b'>') => unimplemented!("Can't parse block/flow indicators!"),
-                Some(b'\n') | Some(b'\r') => unimplemented!("Can't parse non-plain scalar keys!"),
-                Some(err) => break Err(ErrorKind::InvalidChar.with_context(err))?,
-                None => unimplemented!("Can't parse implicit (due to EOF) null values!"),
+                // An escaped quote keeps the first of its two quotes
+                Some(b'\'') if !self.double && iter.peek_n(1)? == Some(b'\'') => {
+                    iter.take(&mut self.scalar)?;
+                    iter.discard()
+                }
+                Some(b'\'') if !self.double => discard_and!(break Ok(())),
+                Some(b'"') if self.double => discard_and!(break Ok(())),
+                Some(b'\\') if self.double => discard_and!(self.parse_escape(iter)?),
+                Some(b'\n') | Some(b'\r') => self.fold(iter, false)?,
+                Some(_) => iter.take(&mut self.scalar)?,
+                None => Err(ErrorKind::InvalidEOF.with_context("unterminated quoted scalar"))?,
             }
         }
     }
-}
 
-impl From<MapWhiteSpace> for MapValue {
-    fn from(prev: MapWhiteSpace) -> Self {
-        Self {
-            indent_floor: prev.indent_floor,
-            value: Default::default(),
+    /// Parses the escape sequence following a '\\' in a double quoted scalar
+    fn parse_escape(&mut self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<()> {
+        make_local!(iter);
+
+        let escape = match iter.peak()? {
+            Some(b'\n') | Some(b'\r') => return self.fold(iter, true),
+            Some(ch) => discard_and!(ch),
+            None => Err(ErrorKind::InvalidEOF.with_context("unterminated quoted scalar"))?,
+        };
+
+        let ch = match escape {
+            b'0' => '\0',
+            b'a' => '\u{07}',
+            b'b' => '\u{08}',
+            b't' | b'\t' => '\t',
+            b'n' => '\n',
+            b'v' => '\u{0B}',
+            b'f' => '\u{0C}',
+            b'r' => '\r',
+            b'e' => '\u{1B}',
+            b' ' => ' ',
+            b'"' => '"',
+            b'/' => '/',
+            b'\\' => '\\',
+            b'N' => '\u{85}',
+            b'_' => '\u{A0}',
+            b'L' => '\u{2028}',
+            b'P' => '\u{2029}',
+            b'x' => parse_hex(iter, 2)?,
+            b'u' => parse_hex(iter, 4)?,
+            b'U' => parse_hex(iter, 8)?,
+            err => Err(ErrorKind::InvalidEscape.with_context(err))?,
+        };
+
+        iter.extend(&mut self.scalar, ch.encode_utf8(&mut [0; 4]).bytes())
+    }
+
+    /// Folds a line break and any empty lines following it. Unescaped
+    /// breaks become a space if no empty lines follow, and drop the
+    /// whitespace preceding them
+    fn fold(&mut self, iter: &mut Scan<impl Iterator<Item = Byte>>, escaped: bool) -> Result<()> {
+        let mut empty = 0;
+        self.multiline = true;
+        line_break(iter)?;
+
+        if !escaped {
+            self.scalar.trim_end();
+        }
+
+        loop {
+            while let Some(b' ') | Some(b'\t') = iter.peak()? {
+                iter.discard()
+            }
+
+            match iter.peak()? {
+                Some(b'\n') | Some(b'\r') => line_break(iter)?,
+                _ => break,
+            }
+
+            empty += 1;
+        }
+
+        match empty {
+            0 if !escaped => iter.push(&mut self.scalar, b' '),
+            n => iter.extend(&mut self.scalar, repeat(b'\n').take(n)),
         }
     }
 }
 
-#[derive(Debug)]
-pub(super) struct MapValue {
-    indent_floor: u16,
-    pub value: Vec<u8>,
+#[derive(Debug, Default)]
+pub(super) struct ScalarBlock {
+    pub scalar: Scratch,
+    pub mark: Mark,
+    pub comment: Option<Node<'static>>,
+    header: bool,
+    folded: bool,
+    chomp: Chomp,
+    // Content indentation, either explicit or detected from the first non-empty line
+    indent: Option<u16>,
 }
 
-impl MapValue {
+impl ScalarBlock {
     pub(super) fn find_next(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
         match iter.peak()? {
-            Some(b'\n') | Some(b'\r') => Ok(Marker::LineEnd),
-            Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'\n', b'\r'], err)))?,
-            None => Ok(Marker::Done),
+            Some(_) => Ok(Marker::LineStart),
+            None => Ok(stream_end(iter)),
         }
     }
 
-    pub(super) fn parse_value(
+    pub(super) fn style(&self) -> ScalarStyle {
+        if self.folded {
+            ScalarStyle::Folded
+        } else {
+            ScalarStyle::Literal
+        }
+    }
+
+    /// Parses a block scalar's header: its indicator followed by optional
+    /// chomping and indentation indicators, in either order, and a comment.
+    /// Does nothing if the header was already parsed
+    pub(super) fn parse_header(
         &mut self,
         iter: &mut Scan<impl Iterator<Item = Byte>>,
     ) -> Result<()> {
         make_local!(iter);
 
-        loop {
+        if self.header {
+            return Ok(());
+        }
+        self.header = true;
+        self.mark = iter.mark();
+
+        if is_key_position(iter) {
+            return Err(missing_value_indicator());
+        }
+
+        let floor = iter.level().map_or(0, |level| level.indent);
+
+        match iter.peak()? {
+            Some(b'|') => discard_and!(self.folded = false),
+            Some(b'>') => discard_and!(self.folded = true),
+            Some(err) => Err(ErrorKind::InvalidChar.with_context((b"|>", err)))?,
+            None => Err(ErrorKind::InvalidEOF)?,
+        }
+
+        for _ in 0..2 {
             match iter.peak()? {
-                Some(ch @ b' ')
-                | Some(ch @ b'\t')
-                | Some(ch @ b'a'..=b'z')
-                | Some(ch @ b'A'..=b'Z')
-                | Some(ch @ b':') => discard_and!(self.value.push(ch)),
-                Some(b'\n') | Some(b'\r') | None => break Ok(()),
-                Some(err) => break Err(ErrorKind::ScalarInvalid.with_context(err))?,
+                Some(b'-') if self.chomp == Chomp::Clip => discard_and!(self.chomp = Chomp::Strip),
+                Some(b'+') if self.chomp == Chomp::Clip => discard_and!(self.chomp = Chomp::Keep),
+                Some(ch @ b'1'..=b'9') if self.indent.is_none() => {
+                    discard_and!(self.indent = Some(floor + u16::from(ch - b'0')))
+                }
+                _ => break,
             }
         }
+
+        let mut separated = false;
+
+        while let Some(b' ') | Some(b'\t') = iter.peak()? {
+            discard_and!(separated = true)
+        }
+
+        if let (Some(b'#'), true) = (iter.peak()?, separated) {
+            self.comment = parse_comment(iter)?;
+        }
+
+        match iter.peak()? {
+            Some(b'\n') | Some(b'\r') => line_break(iter),
+            Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'\n', b'\r'], err)))?,
+            None => Ok(()),
+        }
     }
-}
 
-fn is_whitespace(c: &u8) -> bool {
-    *c == b'\t' || *c == b' '
+    /// Parses the block scalar's content lines, stopping at the first
+    /// non-empty line indented less than the content
+    pub(super) fn parse_content(
+        &mut self,
+        iter: &mut Scan<impl Iterator<Item = Byte>>,
+    ) -> Result<()> {
+        // Content must be indented further than its parent collection
+        let floor = iter.level().map_or(0, |level| level.indent + 1);
+        let mut breaks = 0;
+        let mut content = false;
+        let mut spaced = false;
+
+        loop {
+            while iter.peak()? == Some(b' ') && self.indent.map_or(true, |i| iter.column() < i) {
+                iter.discard()
+            }
+
+            match iter.peak()? {
+                Some(b'\n') | Some(b'\r') => {
+                    line_break(iter)?;
+                    breaks += 1;
+                    continue;
+                }
+                Some(_) => {}
+                None => break,
+            }
+
+            let column = iter.column();
+            let indent = *self.indent.get_or_insert(column.max(floor));
+
+            if column < indent || document_marker(iter)?.is_some() {
+                break;
+            }
+
+            // More indented lines are never folded
+            let more = matches!(iter.peak()?, Some(b' ') | Some(b'\t'));
+            self.join(iter, breaks, content, spaced || more)?;
+
+            while let Some(ch) = iter.peak()? {
+                match ch {
+                    b'\n' | b'\r' => break,
+                    _ => iter.take(&mut self.scalar)?,
+                }
+            }
+
+            content = true;
+            spaced = more;
+            breaks = 0;
+
+            match iter.peak()? {
+                Some(_) => {
+                    line_break(iter)?;
+                    breaks += 1;
+                }
+                None => break,
+            }
+        }
+
+        match self.chomp {
+            Chomp::Clip if content && breaks > 0 => iter.push(&mut self.scalar, b'\n'),
+            Chomp::Strip | Chomp::Clip => Ok(()),
+            Chomp::Keep => iter.extend(&mut self.scalar, repeat(b'\n').take(breaks)),
+        }
+    }
+
+    /// Joins a content line to the preceding content, given the number of
+    /// line breaks between them. Folded scalars turn a lone break between
+    /// two lines into a space, otherwise dropping the first break
+    fn join(
+        &mut self,
+        iter: &Scan<impl Iterator<Item = Byte>>,
+        breaks: usize,
+        content: bool,
+        spaced: bool,
+    ) -> Result<()> {
+        let scalar = &mut self.scalar;
+
+        match breaks {
+            n if !content || !self.folded || spaced => iter.extend(scalar, repeat(b'\n').take(n)),
+            1 => iter.push(scalar, b' '),
+            n => iter.extend(scalar, repeat(b'\n').take(n.saturating_sub(1))),
+        }
+    }
+}
+
+/// How a block scalar's final line break and trailing empty lines are kept
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Chomp {
+    Strip,
+    #[default]
+    Clip,
+    Keep,
+}
+
+impl From<MapWhiteSpace> for ScalarQuoted {
+    fn from(_: MapWhiteSpace) -> Self {
+        Self {
+            value: true,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<NodeProperties> for ScalarQuoted {
+    fn from(prev: NodeProperties) -> Self {
+        Self {
+            value: prev.value,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<AmbiguousScalar> for ScalarLiteral {
+    fn from(prev: AmbiguousScalar) -> Self {
+        let mut scalar = prev.scratch;
+        scalar.trim_end();
+
+        Self {
+            scalar,
+            mark: prev.start.unwrap_or_default(),
+            folded: false,
+        }
+    }
+}
+
+/* Map */
+#[derive(Debug)]
+pub(super) struct MapStart {
+    indent_floor: u16,
+    scratch: Scratch,
+    style: ScalarStyle,
+    // Where the mapping's first key starts
+    pub mark: Mark,
+    // Set if the key is an alias
+    pub alias: bool,
+}
+
+impl MapStart {
+    pub(super) fn find_next(&self, _: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
+        Ok(Marker::MapVerifyKey)
+    }
+
+    pub(super) fn extend_from(
+        current_floor: u16,
+        buffer: Scratch,
+        style: ScalarStyle,
+        mark: Mark,
+    ) -> Self {
+        Self {
+            indent_floor: current_floor,
+            scratch: buffer,
+            style,
+            mark,
+            alias: false,
+        }
+    }
+
+    /// Opens a new mapping unless this key belongs to the innermost
+    /// open mapping. Returns true if a mapping was opened
+    pub(super) fn open_mapping(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<bool> {
+        match iter.level() {
+            Some(level)
+                if level.kind == LevelKind::Mapping && level.indent == self.indent_floor =>
+            {
+                Ok(false)
+            }
+            _ => iter.open(LevelKind::Mapping).and(Ok(true)),
+        }
+    }
+}
+
+impl From<MapStart> for MapVerifyKey {
+    fn from(prev: MapStart) -> Self {
+        Self {
+            key: prev.scratch,
+            style: prev.style,
+            mark: prev.mark,
+            alias: prev.alias,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct MapVerifyKey {
+    pub key: Scratch,
+    pub style: ScalarStyle,
+    pub mark: Mark,
+    pub alias: bool,
+}
+
+impl MapVerifyKey {
+    /// The key's value follows its ':', though it may be empty up to the
+    /// end of the stream
+    pub(super) fn find_next(&self, _: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
+        Ok(Marker::MapWhiteSpace)
+    }
+
+    pub(super) fn parse_key(&mut self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<()> {
+        iter.set_pending(true);
+
+        if self.style != ScalarStyle::Plain {
+            return Ok(());
+        }
+
+        self.key.trim_end();
+
+        Ok(())
+    }
+}
+
+impl From<MapVerifyKey> for MapWhiteSpace {
+    fn from(_: MapVerifyKey) -> Self {
+        Self
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct MapWhiteSpace;
+
+impl MapWhiteSpace {
+    pub(super) fn find_next(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
+        match iter.peak()? {
+            Some(b'\'') | Some(b'"') => Ok(Marker::ScalarQuoted),
+            Some(b'|') | Some(b'>') => Ok(Marker::ScalarBlock),
+            Some(b'[') | Some(b'{') => Ok(Marker::FlowStart),
+            Some(b'&') | Some(b'!') => Ok(Marker::NodeProperties),
+            Some(b'*') => Ok(Marker::Alias),
+            Some(b'\n') | Some(b'\r') | Some(b'#') => Ok(Marker::LineEnd),
+            Some(_) => Ok(Marker::MapValue),
+            None => Ok(Marker::EmptyNode),
+        }
+    }
+
+    pub(super) fn parse_whitespace(
+        &self,
+        iter: &mut Scan<impl Iterator<Item = Byte>>,
+    ) -> Result<()> {
+        while let Some(b' ') | Some(b'\t') = iter.peak()? {
+            iter.discard()
+        }
+
+        Ok(())
+    }
+}
+
+impl From<MapWhiteSpace> for MapValue {
+    fn from(_: MapWhiteSpace) -> Self {
+        Self {
+            value: Default::default(),
+            mark: Default::default(),
+            folded: false,
+        }
+    }
+}
+
+impl From<NodeProperties> for MapValue {
+    fn from(_: NodeProperties) -> Self {
+        Self {
+            value: Default::default(),
+            mark: Default::default(),
+            folded: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct MapValue {
+    pub value: Scratch,
+    pub mark: Mark,
+    // Set if the value's final line break was consumed
+    folded: bool,
+}
+
+impl MapValue {
+    pub(super) fn find_next(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
+        plain_next(iter, self.folded)
+    }
+
+    pub(super) fn parse_value(
+        &mut self,
+        iter: &mut Scan<impl Iterator<Item = Byte>>,
+    ) -> Result<()> {
+        self.mark = iter.mark();
+
+        plain_first(iter)?;
+        plain_line(iter, &mut self.value)?;
+        self.folded = plain_fold(iter, &mut self.value)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct MapEnd;
+
+impl MapEnd {
+    pub(super) fn find_next(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
+        find_line_node(iter)
+    }
+
+    pub(super) fn close(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<()> {
+        close_level(iter, LevelKind::Mapping)
+    }
+}
+
+/// A key given explicitly after a '?', which may be any node
+#[derive(Debug, Default)]
+pub(super) struct MapExplicitKey {
+    pub mark: Mark,
+}
+
+impl MapExplicitKey {
+    pub(super) fn find_next(
+        &self,
+        iter: &mut Scan<impl Iterator<Item = Byte>, Active>,
+    ) -> Result<Marker> {
+        find_entry_node(iter)
+    }
+
+    /// Consumes the '?' starting the key. Returns true if the key
+    /// opens a new mapping
+    pub(super) fn parse_indicator(
+        &mut self,
+        iter: &mut Scan<impl Iterator<Item = Byte>, Active>,
+    ) -> Result<bool> {
+        self.mark = iter.mark();
+
+        match iter.peak()? {
+            Some(b'?') => iter.discard(),
+            Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'?'], err)))?,
+            None => Err(ErrorKind::InvalidEOF)?,
+        }
+
+        let open = match iter.level() {
+            Some(level) if level.indent == iter.current() => match level.kind {
+                LevelKind::Mapping => false,
+                LevelKind::Sequence => Err(ErrorKind::InvalidChar.with_context(b'?'))?,
+            },
+            _ => true,
+        };
+
+        if open {
+            iter.open(LevelKind::Mapping)?
+        }
+
+        Ok(open)
+    }
+
+    /// Skips the whitespace after the indicator, which counts
+    /// towards the indentation of any collection nested in the key
+    pub(super) fn update_indent(
+        &self,
+        iter: &mut Scan<impl Iterator<Item = Byte>, Active>,
+    ) -> Result<()> {
+        let indent = skip_separation(iter)?;
+        iter.update_indent(indent);
+        iter.set_pending(true);
+        iter.set_explicit(true);
+
+        Ok(())
+    }
+}
+
+/// The value of a key given with '?', following a ':' at
+/// the start of a later line
+#[derive(Debug, Default)]
+pub(super) struct MapExplicitValue;
+
+impl MapExplicitValue {
+    pub(super) fn find_next(
+        &self,
+        iter: &mut Scan<impl Iterator<Item = Byte>, Active>,
+    ) -> Result<Marker> {
+        find_entry_node(iter)
+    }
+
+    /// Consumes the ':' starting the value, which must belong to the
+    /// innermost mapping's explicit key
+    pub(super) fn parse_indicator(
+        &self,
+        iter: &mut Scan<impl Iterator<Item = Byte>, Active>,
+    ) -> Result<()> {
+        match iter.level() {
+            Some(level)
+                if level.kind == LevelKind::Mapping
+                    && level.explicit
+                    && level.indent == iter.current() => {}
+            _ => Err(ErrorKind::InvalidChar.with_context(b':'))?,
+        }
+
+        match iter.peak()? {
+            Some(b':') => iter.discard(),
+            Some(err) => Err(ErrorKind::InvalidChar.with_context(([b':'], err)))?,
+            None => Err(ErrorKind::InvalidEOF)?,
+        }
+
+        iter.set_explicit(false);
+
+        Ok(())
+    }
+
+    /// Skips the whitespace after the indicator, which counts
+    /// towards the indentation of any collection nested in the value
+    pub(super) fn update_indent(
+        &self,
+        iter: &mut Scan<impl Iterator<Item = Byte>, Active>,
+    ) -> Result<()> {
+        let indent = skip_separation(iter)?;
+        iter.update_indent(indent);
+        iter.set_pending(true);
+
+        Ok(())
+    }
+}
+
+/* Sequence */
+#[derive(Debug, Default)]
+pub(super) struct SequenceStart {
+    entry: bool,
+    pub mark: Mark,
+}
+
+impl SequenceStart {
+    pub(super) fn find_next(&self, _: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
+        if self.entry {
+            Ok(Marker::SequenceEntry)
+        } else {
+            Ok(Marker::AmbiguousScalar)
+        }
+    }
+
+    /// Consumes a '-', determining whether it is an entry indicator or
+    /// the start of a plain scalar. Returns true if the entry opens a new
+    /// sequence
+    pub(super) fn parse_indicator(
+        &mut self,
+        iter: &mut Scan<impl Iterator<Item = Byte>>,
+    ) -> Result<bool> {
+        make_local!(iter);
+        self.mark = iter.mark();
+
+        match iter.peak()? {
+            Some(b'-') => {}
+            Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'-'], err)))?,
+            None => Err(ErrorKind::InvalidEOF)?,
+        }
+
+        // Otherwise the '-' is left for the plain scalar it starts
+        match iter.peek_n(1)? {
+            Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') | None => {
+                discard_and!(self.entry = true)
+            }
+            Some(_) => {}
+        }
+
+        if !self.entry {
+            return Ok(false);
+        }
+
+        let open = match iter.level() {
+            Some(level) if level.indent == iter.current() => match level.kind {
+                LevelKind::Sequence => false,
+                LevelKind::Mapping if level.pending => true,
+                LevelKind::Mapping => Err(ErrorKind::InvalidChar.with_context(b'-'))?,
+            },
+            _ => true,
+        };
+
+        if open {
+            iter.open(LevelKind::Sequence)?
+        }
+
+        Ok(open)
+    }
+}
+
+impl From<SequenceStart> for AmbiguousScalar {
+    fn from(prev: SequenceStart) -> Self {
+        Self {
+            scratch: Default::default(),
+            start: Some(prev.mark),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct SequenceEntry;
+
+impl SequenceEntry {
+    pub(super) fn find_next(
+        &self,
+        iter: &mut Scan<impl Iterator<Item = Byte>, Active>,
+    ) -> Result<Marker> {
+        find_entry_node(iter)
+    }
+
+    /// Skips the whitespace after the entry indicator, which counts
+    /// towards the indentation of any collection nested in the entry
+    pub(super) fn update_indent(
+        &self,
+        iter: &mut Scan<impl Iterator<Item = Byte>, Active>,
+    ) -> Result<()> {
+        let indent = skip_separation(iter)?;
+        iter.update_indent(indent);
+        iter.set_pending(true);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct SequenceEnd;
+
+impl SequenceEnd {
+    pub(super) fn find_next(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
+        find_line_node(iter)
+    }
+
+    pub(super) fn close(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<()> {
+        close_level(iter, LevelKind::Sequence)
+    }
+}
+
+/* Flow */
+#[derive(Debug, Default)]
+pub(super) struct FlowStart {
+    pub mark: Mark,
+}
+
+impl FlowStart {
+    pub(super) fn find_next(&self, _: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
+        Ok(Marker::FlowEntry)
+    }
+
+    /// Consumes a '[' or '{', opening the flow collection it starts
+    pub(super) fn open(
+        &mut self,
+        iter: &mut Scan<impl Iterator<Item = Byte>>,
+    ) -> Result<LevelKind> {
+        make_local!(iter);
+        self.mark = iter.mark();
+
+        if is_key_position(iter) {
+            return Err(ErrorKind::Unsupported(Feature::FlowKey).into());
+        }
+
+        let kind = match iter.peak()? {
+            Some(b'[') => discard_and!(LevelKind::Sequence),
+            Some(b'{') => discard_and!(LevelKind::Mapping),
+            Some(err) => Err(ErrorKind::InvalidChar.with_context((b"[{", err)))?,
+            None => Err(ErrorKind::InvalidEOF)?,
+        };
+        iter.open_flow(kind)?;
+
+        Ok(kind)
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct FlowEntry {
+    // Set if an empty node stands in for a missing key or value
+    pub empty: bool,
+    pub comment: Option<Node<'static>>,
+}
+
+impl FlowEntry {
+    /// Skips the separators and whitespace between the nodes of the
+    /// innermost flow collection, finding what comes next
+    pub(super) fn find_next(
+        &mut self,
+        iter: &mut Scan<impl Iterator<Item = Byte>>,
+    ) -> Result<Marker> {
+        make_local!(iter);
+
+        let kind = match iter.level() {
+            Some(level) if level.flow.is_some() => level.kind,
+            _ => Err(ErrorKind::StateViolation)?,
+        };
+        let entry = match kind {
+            LevelKind::Mapping => FlowPosition::Key,
+            LevelKind::Sequence => FlowPosition::Value,
+        };
+
+        loop {
+            let position = iter.flow_position().unwrap_or(entry);
+
+            match iter.peak()? {
+                Some(b' ') | Some(b'\t') => iter.discard(),
+                Some(b'\n') | Some(b'\r') => line_break(iter)?,
+                Some(b'#') => {
+                    self.comment = parse_comment(iter)?;
+
+                    if self.comment.is_some() {
+                        break Ok(Marker::FlowEntry);
+                    }
+                }
+                // Properties with no node after them stand on an empty one
+                Some(b',') | Some(b']') | Some(b'}')
+                    if matches!(position, FlowPosition::Key | FlowPosition::Value)
+                        && !iter.properties().is_empty() =>
+                {
+                    let next = match position {
+                        FlowPosition::Key => FlowPosition::Colon,
+                        _ => FlowPosition::Separator,
+                    };
+
+                    break Ok(self.empty(iter, next));
+                }
+                Some(b',') => match position {
+                    FlowPosition::Separator => discard_and!(iter.set_flow_position(entry)),
+                    FlowPosition::Colon | FlowPosition::Value if kind == LevelKind::Mapping => {
+                        break Ok(self.empty(iter, FlowPosition::Separator))
+                    }
+                    _ => Err(ErrorKind::InvalidChar.with_context(b','))?,
+                },
+                Some(b':') => match position {
+                    FlowPosition::Colon => {
+                        discard_and!(iter.set_flow_position(FlowPosition::Value))
+                    }
+                    FlowPosition::Key => break Ok(self.empty(iter, FlowPosition::Colon)),
+                    FlowPosition::Separator if kind == LevelKind::Sequence => {
+                        Err(ErrorKind::Unsupported(Feature::FlowPair))?
+                    }
+                    _ => Err(ErrorKind::InvalidChar.with_context(b':'))?,
+                },
+                // A key may be given explicitly, which changes nothing in flow
+                Some(b'?')
+                    if position == FlowPosition::Key
+                        && iter
+                            .peek_n(1)?
+                            .is_some_and(|next| matches!(next, b' ' | b'\t' | b'\n' | b'\r')) =>
+                {
+                    iter.discard()
+                }
+                Some(b'&') | Some(b'!')
+                    if matches!(position, FlowPosition::Key | FlowPosition::Value) =>
+                {
+                    parse_properties(iter)?
+                }
+                Some(b']') | Some(b'}') => match position {
+                    FlowPosition::Colon | FlowPosition::Value if kind == LevelKind::Mapping => {
+                        break Ok(self.empty(iter, FlowPosition::Separator))
+                    }
+                    _ => break Ok(Marker::FlowEnd),
+                },
+                Some(ch) => {
+                    let next = match position {
+                        FlowPosition::Key => FlowPosition::Colon,
+                        FlowPosition::Value => FlowPosition::Separator,
+                        _ => Err(ErrorKind::InvalidChar.with_context(ch))?,
+                    };
+                    iter.set_flow_position(next);
+
+                    break match ch {
+                        b'[' | b'{' => Ok(Marker::FlowStart),
+                        b'\'' | b'"' => Ok(Marker::ScalarQuoted),
+                        b'|' | b'>' | b'%' | b'@' | b'`' => {
+                            Err(ErrorKind::InvalidChar.with_context(ch))?
+                        }
+                        _ => Ok(Marker::FlowScalar),
+                    };
+                }
+                None => Err(ErrorKind::InvalidEOF.with_context("unterminated flow collection"))?,
+            }
+        }
+    }
+
+    /// Stands an empty node in for a missing key or value
+    fn empty(&mut self, iter: &mut Scan<impl Iterator<Item = Byte>>, next: FlowPosition) -> Marker {
+        self.empty = true;
+        iter.set_flow_position(next);
+
+        Marker::FlowEntry
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct FlowScalar {
+    pub scalar: Scratch,
+    pub mark: Mark,
+    // Set if the scalar is an alias's name instead
+    pub alias: bool,
+}
+
+impl FlowScalar {
+    pub(super) fn find_next(&self, _: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
+        Ok(Marker::FlowEntry)
+    }
+
+    /// Parses a plain scalar inside a flow collection, which is ended by
+    /// flow indicators, a ':' followed by whitespace and line breaks not
+    /// followed by more of the scalar
+    pub(super) fn parse_scalar(
+        &mut self,
+        iter: &mut Scan<impl Iterator<Item = Byte>>,
+    ) -> Result<()> {
+        make_local!(iter);
+        self.mark = iter.mark();
+
+        if iter.peak()? == Some(b'*') {
+            self.alias = true;
+            self.scalar = parse_alias(iter)?;
+
+            return Ok(());
+        }
+
+        plain_first(iter)?;
+
+        loop {
+            match iter.peak()? {
+                Some(b',') | Some(b'[') | Some(b']') | Some(b'{') | Some(b'}') | None => break,
+                Some(b'\n') | Some(b'\r') => {
+                    if !self.fold(iter)? {
+                        break;
+                    }
+                }
+                Some(b'#') if self.scalar.ends_with_whitespace() => break,
+                Some(b':')
+                    if !iter
+                        .peek_n(1)?
+                        .is_some_and(|next| is_plain_safe(next, true)) =>
+                {
+                    discard_and!(break Self::value_indicator(iter)?)
+                }
+                Some(err) if !is_printable(err) => Err(ErrorKind::InvalidChar.with_context(err))?,
+                Some(_) => iter.take(&mut self.scalar)?,
+            }
+        }
+
+        self.scalar.trim_end();
+
+        Ok(())
+    }
+
+    /// Folds the line breaks ending one of the scalar's lines, returning
+    /// false if the next non-empty line doesn't continue the scalar
+    fn fold(&mut self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<bool> {
+        let mut breaks = 0;
+
+        while let Some(b'\n') | Some(b'\r') = iter.peak()? {
+            line_break(iter)?;
+            breaks += 1;
+
+            while let Some(b' ') | Some(b'\t') = iter.peak()? {
+                iter.discard()
+            }
+        }
+
+        match iter.peak()? {
+            Some(b',') | Some(b'[') | Some(b']') | Some(b'{') | Some(b'}') | None => Ok(false),
+            Some(b'#') | Some(b':') => Ok(false),
+            Some(_) => {
+                self.scalar.trim_end();
+                fold_breaks(iter, &mut self.scalar, breaks)?;
+
+                Ok(true)
+            }
+        }
+    }
+
+    /// Handles a ':' consumed while parsing the scalar, which makes the
+    /// scalar a key
+    fn value_indicator(iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<()> {
+        match iter.flow_position() {
+            Some(FlowPosition::Colon) => {
+                iter.set_flow_position(FlowPosition::Value);
+                Ok(())
+            }
+            Some(FlowPosition::Separator) => Err(ErrorKind::Unsupported(Feature::FlowPair))?,
+            _ => Err(ErrorKind::InvalidChar.with_context(b':'))?,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub(super) struct FlowEnd {
+    pub mark: Mark,
+}
+
+impl FlowEnd {
+    pub(super) fn find_next(&self, iter: &mut Scan<impl Iterator<Item = Byte>>) -> Result<Marker> {
+        if iter.flow_position().is_some() {
+            return Ok(Marker::FlowEntry);
+        }
+
+        // Back in the block context, the rest of the line must be empty
+        while let Some(b' ') | Some(b'\t') = iter.peak()? {
+            iter.discard()
+        }
+
+        match iter.peak()? {
+            Some(b'\n') | Some(b'\r') | Some(b'#') => Ok(Marker::LineEnd),
+            Some(b':') if is_block_indicator(iter, b':')? => {
+                Err(ErrorKind::Unsupported(Feature::FlowKey))?
+            }
+            Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'\n', b'\r'], err)))?,
+            None => Ok(stream_end(iter)),
+        }
+    }
+
+    /// Consumes a ']' or '}', closing the innermost flow collection
+    pub(super) fn close(
+        &mut self,
+        iter: &mut Scan<impl Iterator<Item = Byte>>,
+    ) -> Result<LevelKind> {
+        make_local!(iter);
+        self.mark = iter.mark();
+
+        let (kind, ch) = match iter.peak()? {
+            Some(b']') => (LevelKind::Sequence, b']'),
+            Some(b'}') => (LevelKind::Mapping, b'}'),
+            Some(err) => Err(ErrorKind::InvalidChar.with_context((b"]}", err)))?,
+            None => Err(ErrorKind::InvalidEOF)?,
+        };
+
+        match iter.close() {
+            Some(level) if level.flow.is_some() && level.kind == kind => discard_and!(Ok(kind)),
+            Some(_) => Err(ErrorKind::InvalidChar.with_context(ch))?,
+            None => Err(ErrorKind::StateViolation)?,
+        }
+    }
+}
+
+/// Checks the first character of a plain scalar, which can't be an
+/// indicator unless it is a '-', '?' or ':' followed by a safe character
+fn plain_first<S>(iter: &mut Scan<impl Iterator<Item = Byte>, S>) -> Result<()> {
+    let flow = iter.flow_position().is_some();
+
+    match iter.peak()? {
+        Some(b'-') | Some(b'?') | Some(b':')
+            if iter
+                .peek_n(1)?
+                .is_some_and(|next| is_plain_safe(next, flow)) =>
+        {
+            Ok(())
+        }
+        Some(err) if is_indicator(err) => Err(ErrorKind::InvalidChar.with_context(err))?,
+        _ => Ok(()),
+    }
+}
+
+/// Parses the rest of a line of a block plain scalar, stopping at a line
+/// break or comment. Only the first line of a plain scalar can be a key,
+/// so a ':' followed by whitespace is an error
+fn plain_line<S>(
+    iter: &mut Scan<impl Iterator<Item = Byte>, S>,
+    scalar: &mut Scratch,
+) -> Result<()> {
+    loop {
+        match iter.peak()? {
+            Some(b'\n') | Some(b'\r') | None => break,
+            Some(b'#') if scalar.ends_with_whitespace() => break,
+            Some(b':')
+                if !iter
+                    .peek_n(1)?
+                    .is_some_and(|next| is_plain_safe(next, false)) =>
+            {
+                Err(ErrorKind::InvalidChar.with_context(b':'))?
+            }
+            Some(err) if !is_printable(err) => Err(ErrorKind::InvalidChar.with_context(err))?,
+            Some(_) => iter.take(scalar)?,
+        }
+    }
+
+    scalar.trim_end();
+
+    Ok(())
+}
+
+/// Folds any following lines indented past the scalar's parent collection
+/// into a block plain scalar. Returns true if a line break was consumed, in
+/// which case the scanner is left at the content of the next non-empty line
+fn plain_fold<S>(
+    iter: &mut Scan<impl Iterator<Item = Byte>, S>,
+    scalar: &mut Scratch,
+) -> Result<bool> {
+    let floor = iter.level().map(|level| level.indent);
+    let mut folded = false;
+
+    while let Some(b'\n') | Some(b'\r') = iter.peak()? {
+        let mut breaks = 0;
+        let mut tabbed = false;
+        folded = true;
+
+        while let Some(b'\n') | Some(b'\r') = iter.peak()? {
+            line_break(iter)?;
+            breaks += 1;
+            tabbed = false;
+
+            while let Some(ch @ b' ') | Some(ch @ b'\t') = iter.peak()? {
+                tabbed |= ch == b'\t';
+                iter.discard()
+            }
+        }
+
+        match iter.peak()? {
+            Some(b'#') | None => break,
+            // The line holds the next node, so its whitespace was indentation
+            Some(_) if floor.is_some_and(|floor| iter.column() <= floor) && tabbed => {
+                Err(ErrorKind::InvalidIndent.with_context("tabs can't be used for indentation"))?
+            }
+            Some(_) if floor.is_some_and(|floor| iter.column() <= floor) => break,
+            Some(_) if document_marker(iter)?.is_some() => break,
+            Some(_) => {}
+        }
+
+        fold_breaks(iter, scalar, breaks)?;
+        plain_line(iter, scalar)?;
+    }
+
+    Ok(folded)
+}
+
+/// Finds what follows a block plain scalar, given whether its final line
+/// break was consumed
+fn plain_next<S>(iter: &mut Scan<impl Iterator<Item = Byte>, S>, folded: bool) -> Result<Marker> {
+    match iter.peak()? {
+        Some(_) if folded => Ok(Marker::LineStart),
+        Some(b'#') => Ok(Marker::LineEnd),
+        Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'\n', b'\r'], err)))?,
+        None => Ok(stream_end(iter)),
+    }
+}
+
+/// Joins two lines of a plain scalar separated by the given number of line
+/// breaks: a lone break becomes a space, otherwise the first is dropped
+fn fold_breaks<S>(
+    iter: &Scan<impl Iterator<Item = Byte>, S>,
+    scalar: &mut Scratch,
+    breaks: usize,
+) -> Result<()> {
+    match breaks {
+        1 => iter.push(scalar, b' '),
+        n => iter.extend(scalar, repeat(b'\n').take(n.saturating_sub(1))),
+    }
+}
+
+/// Decides what follows a line's indentation: either the innermost open
+/// collection must be closed, or the line's node begins
+fn find_line_node<S>(iter: &mut Scan<impl Iterator<Item = Byte>, S>) -> Result<Marker> {
+    let next = iter.peak()?;
+    let indent = iter.current();
+    // Every collection is closed at the end of the document
+    let end = next.is_none() || document_marker(iter)?.is_some();
+    let key = is_block_indicator(iter, b'?')?;
+    let value = is_block_indicator(iter, b':')?;
+
+    if let Some(level) = iter.level() {
+        let close = match next {
+            Some(ch) if !end => {
+                level.indent > indent
+                    || (level.indent == indent && level.kind == LevelKind::Sequence && ch != b'-')
+            }
+            _ => true,
+        };
+
+        // A collection's missing node is empty, not left out
+        if close {
+            return Ok(match level.pending || level.explicit {
+                true => Marker::EmptyNode,
+                false => close_marker(level.kind),
+            });
+        }
+
+        // Any deeper line must hold the node of a pending key or entry
+        if level.indent < indent && !level.pending {
+            return Err(ErrorKind::InvalidIndent
+                .with_context((level.indent, indent))
+                .into());
+        }
+
+        // A block sequence may be the node of a key at the same indentation
+        if level.indent == indent
+            && level.pending
+            && !(level.kind == LevelKind::Mapping && next == Some(b'-'))
+        {
+            return Ok(Marker::EmptyNode);
+        }
+
+        // An explicit key without a value before the mapping's next key
+        if level.indent == indent && level.explicit && !value {
+            return Ok(Marker::EmptyNode);
+        }
+    }
+
+    match next {
+        // Properties may be all there is to a document's node
+        _ if end && !iter.properties().is_empty() => Ok(Marker::EmptyNode),
+        _ if end => Ok(Marker::DocumentEnd),
+        // Anything after the root node must start a new document
+        _ if iter.level().is_none() && iter.has_root() => Err(ErrorKind::InvalidChar
+            .with_context("expected a document marker after the root node")
+            .into()),
+        _ if key => Ok(Marker::MapExplicitKey),
+        _ if value => Ok(Marker::MapExplicitValue),
+        Some(b'-') => Ok(Marker::SequenceStart),
+        Some(b'&') | Some(b'!') => Ok(Marker::NodeProperties),
+        Some(b'*') => Ok(Marker::Alias),
+        Some(b'\'') | Some(b'"') => Ok(Marker::ScalarQuoted),
+        Some(b'|') | Some(b'>') => Ok(Marker::ScalarBlock),
+        Some(b'[') | Some(b'{') => Ok(Marker::FlowStart),
+        _ => Ok(Marker::AmbiguousScalar),
+    }
+}
+
+/// Checks whether the next node stands where the innermost block mapping
+/// expects a key, rather than the node of a key or entry
+fn is_key_position<S>(iter: &Scan<impl Iterator<Item = Byte>, S>) -> bool {
+    iter.level().is_some_and(|level| {
+        level.flow.is_none() && level.kind == LevelKind::Mapping && !level.pending
+    })
+}
+
+/// Error for a node standing where its mapping expects a key, which
+/// isn't followed by a ':'
+fn missing_value_indicator() -> Error {
+    ErrorKind::InvalidChar
+        .with_context("expected a ':' after the mapping's key")
+        .into()
+}
+
+/// Finds the node following the indicator of a block sequence's entry,
+/// or of a mapping's explicit key or value
+fn find_entry_node(iter: &mut Scan<impl Iterator<Item = Byte>, Active>) -> Result<Marker> {
+    if is_block_indicator(iter, b'?')? {
+        return Ok(Marker::MapExplicitKey);
+    }
+
+    match iter.peak()? {
+        Some(b'\n') | Some(b'\r') | Some(b'#') => Ok(Marker::LineEnd),
+        Some(b'-') => Ok(Marker::SequenceStart),
+        Some(b'&') | Some(b'!') => Ok(Marker::NodeProperties),
+        Some(b'*') => Ok(Marker::Alias),
+        Some(b'\'') | Some(b'"') => Ok(Marker::ScalarQuoted),
+        Some(b'|') | Some(b'>') => Ok(Marker::ScalarBlock),
+        Some(b'[') | Some(b'{') => Ok(Marker::FlowStart),
+        Some(_) => Ok(Marker::AmbiguousScalar),
+        None => Ok(Marker::EmptyNode),
+    }
+}
+
+/// Checks for the given block indicator, which unlike the start of a
+/// plain scalar is followed by whitespace or the end of the line
+fn is_block_indicator<S>(iter: &mut Scan<impl Iterator<Item = Byte>, S>, ch: u8) -> Result<bool> {
+    if iter.peak()? != Some(ch) {
+        return Ok(false);
+    }
+
+    match iter.peek_n(1)? {
+        Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') | None => Ok(true),
+        Some(_) => Ok(false),
+    }
+}
+
+/// Checks for a document marker, a '---' or '...' at the start of a line
+/// followed by whitespace, returning the character it repeats
+fn document_marker<S>(iter: &mut Scan<impl Iterator<Item = Byte>, S>) -> Result<Option<u8>> {
+    let ch = match iter.peak()? {
+        Some(ch @ b'-') | Some(ch @ b'.') if iter.column() == 0 => ch,
+        _ => return Ok(None),
+    };
+
+    for n in 1..3 {
+        if iter.peek_n(n)? != Some(ch) {
+            return Ok(None);
+        }
+    }
+
+    match iter.peek_n(3)? {
+        Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') | None => Ok(Some(ch)),
+        Some(_) => Ok(None),
+    }
+}
+
+/// Consumes a document marker found by [`document_marker`]
+fn skip_marker<S>(iter: &mut Scan<impl Iterator<Item = Byte>, S>) {
+    for _ in 0..3 {
+        iter.discard()
+    }
+}
+
+/// Closes the innermost open collection, which must be of the given kind
+fn close_level<S>(iter: &mut Scan<impl Iterator<Item = Byte>, S>, kind: LevelKind) -> Result<()> {
+    match iter.close() {
+        Some(level) if level.kind == kind && !(level.pending || level.explicit) => Ok(()),
+        _ => Err(ErrorKind::StateViolation)?,
+    }
+}
+
+/// Marker for the end of the stream, closing any collections left open
+/// once their missing nodes are found
+fn stream_end<S>(iter: &Scan<impl Iterator<Item = Byte>, S>) -> Marker {
+    match iter.level() {
+        Some(level) if level.pending || level.explicit => Marker::EmptyNode,
+        Some(level) => close_marker(level.kind),
+        None if !iter.properties().is_empty() => Marker::EmptyNode,
+        None => Marker::DocumentEnd,
+    }
+}
+
+/// Consumes the indentation at the start of a line, returning the
+/// column of the line's content. Only spaces indent, tabs may follow
+/// them on lines without content
+fn count_indent(iter: &mut Scan<impl Iterator<Item = Byte>, Active>) -> Result<u16> {
+    while let Some(b' ') = iter.peak()? {
+        iter.discard()
+    }
+
+    if iter.peak()? != Some(b'\t') {
+        return Ok(iter.column());
+    }
+
+    let indent = skip_separation(iter)?;
+
+    match iter.peak()? {
+        Some(b'\n') | Some(b'\r') | Some(b'#') | None => Ok(indent),
+        Some(_) => {
+            Err(ErrorKind::InvalidIndent.with_context("tabs can't be used for indentation"))?
+        }
+    }
+}
+
+/// Consumes the whitespace separating an indicator from what follows
+/// it, returning the column it ends at
+fn skip_separation(iter: &mut Scan<impl Iterator<Item = Byte>, Active>) -> Result<u16> {
+    while let Some(b' ') | Some(b'\t') = iter.peak()? {
+        iter.discard()
+    }
+
+    Ok(iter.column())
+}
+
+/// Consumes a comment, from its '#' up to the end of the line. The
+/// comment is returned only if comments are emitted
+fn parse_comment<S>(
+    iter: &mut Scan<impl Iterator<Item = Byte>, S>,
+) -> Result<Option<Node<'static>>> {
+    make_local!(iter);
+    let mark = iter.mark();
+    let mut text = Vec::new();
+
+    match iter.peak()? {
+        Some(b'#') => discard_and!(()),
+        Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'#'], err)))?,
+        None => Err(ErrorKind::InvalidEOF)?,
+    }
+
+    while let Some(ch) = iter.peak()? {
+        match ch {
+            b'\n' | b'\r' => break,
+            ch => discard_and!(text.push(ch)),
+        }
+    }
+
+    if !iter.config().comments {
+        return Ok(None);
+    }
+
+    let text = String::from_utf8(text)
+        .map_err(|_| ErrorKind::InvalidChar.with_context("comment is not valid UTF-8"))?;
+
+    Ok(Some(NodeKind::Comment(text.into()).at(mark)))
+}
+
+/// Parses a node's properties along with any whitespace following them,
+/// leaving them for the node to take
+fn parse_properties<S>(iter: &mut Scan<impl Iterator<Item = Byte>, S>) -> Result<()> {
+    make_local!(iter);
+
+    if !iter.properties().is_empty() {
+        Err(ErrorKind::InvalidChar.with_context("a node's properties must be given together"))?
+    }
+
+    let mut properties = Properties {
+        line: iter.mark().line,
+        ..Default::default()
+    };
+
+    loop {
+        match iter.peak()? {
+            Some(b'&') if properties.anchor.is_none() => discard_and!({
+                let name = parse_name(iter)?;
+                properties.anchor = Some(iter.resolve(name)?);
+            }),
+            Some(b'&') => {
+                Err(ErrorKind::InvalidChar.with_context("a node can only have one anchor"))?
+            }
+            Some(b'!') if properties.tag.is_none() => {
+                properties.tag = Some(parse_tag(iter)?.into());
+            }
+            Some(b'!') => Err(ErrorKind::InvalidTag.with_context("a node can only have one tag"))?,
+            _ => break,
+        }
+
+        while let Some(b' ') | Some(b'\t') = iter.peak()? {
+            iter.discard()
+        }
+    }
+
+    iter.set_properties(properties);
+
+    Ok(())
+}
+
+/// Parses a tag, from its '!' up to the end of its suffix, resolving its
+/// handle against the document's directives
+fn parse_tag<S>(iter: &mut Scan<impl Iterator<Item = Byte>, S>) -> Result<String> {
+    let mut scratch = Scratch::default();
+
+    match iter.peak()? {
+        Some(b'!') => iter.discard(),
+        Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'!'], err)))?,
+        None => Err(ErrorKind::InvalidEOF)?,
+    }
+
+    // Verbatim tags are used as given
+    if iter.peak()? == Some(b'<') {
+        iter.discard();
+
+        loop {
+            match iter.peak()? {
+                Some(b'>') => break iter.discard(),
+                Some(ch) if is_uri_char(ch) => iter.take(&mut scratch)?,
+                Some(err) => Err(ErrorKind::InvalidTag.with_context(err))?,
+                None => Err(ErrorKind::InvalidEOF)?,
+            }
+        }
+
+        return match decode_uri(&iter.resolve(scratch)?)? {
+            tag if tag.is_empty() || tag == "!" => {
+                Err(ErrorKind::InvalidTag.with_context("invalid verbatim tag"))?
+            }
+            tag => Ok(tag),
+        };
+    }
+
+    while let Some(ch) = iter.peak()? {
+        match is_uri_char(ch) && !matches!(ch, b',' | b'[' | b']' | b'{' | b'}') {
+            true => iter.take(&mut scratch)?,
+            false => break,
+        }
+    }
+
+    match iter.peak()? {
+        Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') | None => {}
+        Some(b',') | Some(b']') | Some(b'}') if iter.flow_position().is_some() => {}
+        Some(err) => Err(ErrorKind::InvalidChar.with_context(err))?,
+    }
+
+    // The shorthand's handle is '!', '!!' or a named '!name!'
+    let shorthand = iter.resolve(scratch)?;
+    let (handle, suffix) = match shorthand.find('!') {
+        Some(end) => (format!("!{}", &shorthand[..=end]), &shorthand[end + 1..]),
+        None => (String::from("!"), shorthand.as_ref()),
+    };
+
+    // The non-specific tag '!' is kept as is
+    if handle == "!" && suffix.is_empty() {
+        return Ok(handle);
+    }
+
+    if !is_tag_handle(&handle) || suffix.is_empty() || suffix.contains('!') {
+        Err(ErrorKind::InvalidTag.with_context(format!("!{}", shorthand).as_str()))?
+    }
+
+    match iter.directives().prefix(&handle) {
+        Some(prefix) => Ok(format!("{}{}", prefix, decode_uri(suffix)?)),
+        None => Err(ErrorKind::InvalidTag.with_context("unknown tag handle"))?,
+    }
+}
+
+/// Decodes the %-escaped bytes of a tag
+fn decode_uri(uri: &str) -> Result<String> {
+    let mut bytes = Vec::with_capacity(uri.len());
+    let mut iter = uri.bytes();
+
+    while let Some(ch) = iter.next() {
+        let byte = match ch {
+            b'%' => iter
+                .next()
+                .zip(iter.next())
+                .and_then(|(high, low)| {
+                    let digits = [high, low];
+                    let digits = str::from_utf8(&digits).ok()?;
+
+                    u8::from_str_radix(digits, 16).ok()
+                })
+                .ok_or_else(|| ErrorKind::InvalidTag.with_context("invalid escape in tag"))?,
+            ch => ch,
+        };
+
+        bytes.push(byte)
+    }
+
+    Ok(String::from_utf8(bytes)
+        .map_err(|_| ErrorKind::InvalidTag.with_context("invalid UTF-8 in tag"))?)
+}
+
+/// Parses an alias, from its '*' up to the end of the anchor it names
+fn parse_alias<S>(iter: &mut Scan<impl Iterator<Item = Byte>, S>) -> Result<Scratch> {
+    make_local!(iter);
+
+    if !iter.properties().is_empty() {
+        Err(ErrorKind::InvalidChar.with_context("an alias can't have properties"))?
+    }
+
+    match iter.peak()? {
+        Some(b'*') => discard_and!(parse_name(iter)),
+        Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'*'], err)))?,
+        None => Err(ErrorKind::InvalidEOF)?,
+    }
+}
+
+/// Parses the name of an anchor or alias, which ends at whitespace, a
+/// flow indicator or a ':' which ends a mapping key
+fn parse_name<S>(iter: &mut Scan<impl Iterator<Item = Byte>, S>) -> Result<Scratch> {
+    let mut name = Scratch::default();
+
+    loop {
+        match iter.peak()? {
+            Some(b':')
+                if !iter
+                    .peek_n(1)?
+                    .is_some_and(|next| is_plain_safe(next, true)) =>
+            {
+                break
+            }
+            Some(b' ') | Some(b'\t') | Some(b'\n') | Some(b'\r') | None => break,
+            Some(ch) if is_plain_safe(ch, true) => iter.take(&mut name)?,
+            Some(err) if !is_printable(err) => Err(ErrorKind::InvalidChar.with_context(err))?,
+            _ => break,
+        }
+    }
+
+    if name.is_empty() {
+        Err(ErrorKind::InvalidChar.with_context("an anchor's name can't be empty"))?
+    }
+
+    Ok(name)
+}
+
+/// Parses a directive, from its '%' up to the end of the line, adding it to
+/// the given directives. Unknown directives are ignored, though they and
+/// unknown minor versions of YAML are returned as warnings
+fn parse_directive(
+    iter: &mut Scan<impl Iterator<Item = Byte>, Active>,
+    directives: &mut Directives,
+) -> Result<Option<Error>> {
+    make_local!(iter);
+    let mark = iter.mark();
+
+    match iter.peak()? {
+        Some(b'%') => discard_and!(()),
+        Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'%'], err)))?,
+        None => Err(ErrorKind::InvalidEOF)?,
+    }
+
+    let warning = match parse_word(iter)?.as_str() {
+        "YAML" if directives.version.is_some() => {
+            Err(ErrorKind::InvalidDirective.with_context("duplicate %YAML directive"))?
+        }
+        "YAML" => {
+            let version = parse_version(iter)?;
+            directives.version = Some(version);
+
+            match version {
+                Version { major: 1, minor: 1 } | Version { major: 1, minor: 2 } => None,
+                Version { major: 1, .. } => Some(ErrorKind::UnsupportedVersion(version).into()),
+                _ => Err(ErrorKind::UnsupportedVersion(version))?,
+            }
+        }
+        "TAG" => {
+            let handle = parse_word(iter)?;
+            let prefix = parse_word(iter)?;
+
+            if !is_tag_handle(&handle) {
+                Err(ErrorKind::InvalidDirective.with_context("invalid tag handle"))?
+            }
+            if prefix.is_empty() || prefix.starts_with([',', '[', ']', '{', '}']) {
+                Err(ErrorKind::InvalidDirective.with_context("invalid tag prefix"))?
+            }
+            if directives
+                .tags
+                .iter()
+                .any(|(registered, _)| *registered == handle)
+            {
+                Err(ErrorKind::InvalidDirective.with_context("duplicate %TAG handle"))?
+            }
+
+            directives.tags.push((handle, prefix));
+            None
+        }
+        _ => {
+            while let Some(ch) = iter.peak()? {
+                match ch {
+                    b'\n' | b'\r' => break,
+                    _ => iter.discard(),
+                }
+            }
+
+            Some(
+                ErrorKind::InvalidDirective
+                    .with_context("unknown directive ignored")
+                    .into(),
+            )
+        }
+    };
+
+    // Only a comment may follow the directive's parameters
+    while let Some(b' ') | Some(b'\t') = iter.peak()? {
+        iter.discard()
+    }
+
+    if let Some(b'#') = iter.peak()? {
+        parse_comment(iter)?;
+    }
+
+    match iter.peak()? {
+        Some(b'\n') | Some(b'\r') | None => Ok(warning.map(|warning: Error| warning.at(mark))),
+        Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'\n', b'\r'], err)))?,
+    }
+}
+
+/// Parses a directive's name or parameter, skipping any whitespace
+/// preceding it
+fn parse_word(iter: &mut Scan<impl Iterator<Item = Byte>, Active>) -> Result<String> {
+    let mut word = Vec::new();
+
+    while let Some(b' ') | Some(b'\t') = iter.peak()? {
+        iter.discard()
+    }
+
+    while let Some(ch) = iter.peak()? {
+        match ch {
+            b' ' | b'\t' | b'\n' | b'\r' => break,
+            ch if !is_printable(ch) => Err(ErrorKind::InvalidChar.with_context(ch))?,
+            ch => {
+                word.push(ch);
+                iter.discard()
+            }
+        }
+    }
+
+    String::from_utf8(word).map_err(|_| {
+        ErrorKind::InvalidChar
+            .with_context("directive is not valid UTF-8")
+            .into()
+    })
+}
+
+/// Parses a %YAML directive's version, a major and minor number
+fn parse_version(iter: &mut Scan<impl Iterator<Item = Byte>, Active>) -> Result<Version> {
+    let word = parse_word(iter)?;
+    let invalid = || ErrorKind::InvalidDirective.with_context("invalid YAML version");
+
+    let (major, minor) = word.split_once('.').ok_or_else(invalid)?;
+    let number = |part: &str| match part.bytes().all(|ch| ch.is_ascii_digit()) {
+        true => part.parse::<u32>().map_err(|_| invalid()),
+        false => Err(invalid()),
+    };
+
+    Ok(Version {
+        major: number(major)?,
+        minor: number(minor)?,
+    })
+}
+
+/// Checks a tag handle is either '!', '!!' or a named handle, a word
+/// between two '!'
+fn is_tag_handle(handle: &str) -> bool {
+    match handle
+        .strip_prefix('!')
+        .and_then(|rest| rest.strip_suffix('!'))
+    {
+        Some(name) => name
+            .bytes()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == b'-'),
+        None => handle == "!",
+    }
+}
+
+/// Parses the given number of hex digits into a unicode character
+fn parse_hex(iter: &mut Scan<impl Iterator<Item = Byte>>, digits: usize) -> Result<char> {
+    make_local!(iter);
+    let mut code = 0;
+
+    for _ in 0..digits {
+        let digit = match iter.peak()? {
+            Some(ch) => discard_and!((ch as char)
+                .to_digit(16)
+                .ok_or_else(|| ErrorKind::InvalidEscape.with_context(ch))?),
+            None => Err(ErrorKind::InvalidEOF.with_context("unterminated quoted scalar"))?,
+        };
+
+        code = code << 4 | digit;
+    }
+
+    Ok(char::from_u32(code)
+        .ok_or_else(|| ErrorKind::InvalidEscape.with_context("invalid unicode code point"))?)
+}
+
+/// Consumes a single line break, either '\n' or '\r\n'
+fn line_break<S>(iter: &mut Scan<impl Iterator<Item = Byte>, S>) -> Result<()> {
+    make_local!(iter);
+
+    match iter.peak()? {
+        Some(b'\n') => discard_and!(Ok(())),
+        Some(b'\r') => discard_and!(match iter.peak()? {
+            Some(b'\n') => discard_and!(Ok(())),
+            _ => Err(ErrorKind::SoloCarriageReturn)?,
+        }),
+        Some(err) => Err(ErrorKind::InvalidChar.with_context(([b'\n', b'\r'], err)))?,
+        None => Err(ErrorKind::InvalidEOL.with_context(b'\n'))?,
+    }
+}
+
+fn close_marker(kind: LevelKind) -> Marker {
+    match kind {
+        LevelKind::Mapping => Marker::MapEnd,
+        LevelKind::Sequence => Marker::SequenceEnd,
+    }
+}
+
+pub(super) fn is_whitespace(c: &u8) -> bool {
+    *c == b'\t' || *c == b' '
+}
+
+/// Characters which can't start a plain scalar
+pub(super) fn is_indicator(c: u8) -> bool {
+    matches!(
+        c,
+        b'-' | b'?'
+            | b':'
+            | b','
+            | b'['
+            | b']'
+            | b'{'
+            | b'}'
+            | b'#'
+            | b'&'
+            | b'*'
+            | b'!'
+            | b'|'
+            | b'>'
+            | b'\''
+            | b'"'
+            | b'%'
+            | b'@'
+            | b'`'
+    )
+}
+
+/// Anything but control characters, though tabs are allowed. Bytes of
+/// multibyte UTF-8 sequences are all printable
+pub(super) fn is_printable(c: u8) -> bool {
+    c == b'\t' || (c >= 0x20 && c != 0x7F)
+}
+
+/// Characters allowed in a tag, escaped bytes start with '%'
+pub(super) fn is_uri_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"-%#;/?:@&=+$,_.!~*'()[]{}".contains(&c)
+}
+
+/// Characters which may follow a ':' or indicator inside a plain scalar.
+/// Flow indicators end plain scalars inside flow collections
+pub(super) fn is_plain_safe(c: u8, flow: bool) -> bool {
+    let indicator = matches!(c, b',' | b'[' | b']' | b'{' | b'}');
+
+    is_printable(c) && !is_whitespace(&c) && !(flow && indicator)
 }
 
 // #[derive(Debug, Default)]
@@ -0,0 +1,945 @@
+use std::{borrow::Cow, convert::TryFrom, io, iter::FromIterator, ops, str::FromStr};
+
+use super::{
+    compose::{Aliases, Merges},
+    error::{Error, ErrorKind, Result},
+    mark::Mark,
+    node::{Node, NodeKind, ScalarStyle},
+    parser::Parser,
+    schema::{self, Core, Resolved, Schema},
+};
+
+/// An owned YAML node, with plain scalars resolved under the core schema
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// Integers too large for an i64 are read as floats
+    Int(i64),
+    Float(f64),
+    String(String),
+    Sequence(Sequence),
+    Mapping(Mapping),
+    /// A node with an explicit tag
+    Tagged(Box<Tagged>),
+}
+
+pub type Sequence = Vec<Value>;
+
+/// A mapping which keeps its entries in insertion order.
+/// Lookups are linear in the number of entries
+#[derive(Debug, Clone, Default)]
+pub struct Mapping {
+    entries: Vec<(Value, Value)>,
+}
+
+/// A value and the tag it was given
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tagged {
+    pub tag: String,
+    pub value: Value,
+}
+
+static NULL: Value = Value::Null;
+
+impl Value {
+    /// Builds the value of a single document stream from its nodes,
+    /// as yielded by the parser. An empty stream is null
+    pub fn from_nodes<'a, I>(nodes: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = Result<Node<'a>>>,
+    {
+        Self::from_nodes_with(nodes, Core)
+    }
+
+    /// Builds the value of a single document stream from its nodes,
+    /// resolving plain scalars with the given schema
+    pub fn from_nodes_with<'a, I, S>(nodes: I, schema: S) -> Result<Self>
+    where
+        I: IntoIterator<Item = Result<Node<'a>>>,
+        S: Schema,
+    {
+        Builder {
+            nodes: Merges::new(Aliases::new(nodes.into_iter()), &schema),
+            schema: &schema,
+            mark: Mark::default(),
+        }
+        .stream()
+    }
+
+    /// Builds the value of a single document from its nodes, starting
+    /// at the document's start
+    pub(super) fn from_document<'a, S: Schema>(nodes: Vec<Node<'a>>, schema: S) -> Result<Self> {
+        let nodes = Aliases::new(nodes.into_iter().map(Ok));
+        let mut builder = Builder {
+            nodes: Merges::new(nodes, &schema),
+            schema: &schema,
+            mark: Mark::default(),
+        };
+
+        match builder.next()? {
+            Node {
+                kind: NodeKind::DocumentStart(_),
+                ..
+            } => builder.document(),
+            node => Err(unexpected(&node.kind, "a document").at(node.mark)),
+        }
+    }
+
+    /// Parses the value of the given slice
+    pub fn from_slice(slice: &[u8]) -> Result<Self> {
+        Self::from_nodes(Parser::from_slice(slice))
+    }
+
+    /// Parses the value of the given stream
+    pub fn from_reader<R: io::Read>(reader: R) -> Result<Self> {
+        Self::from_nodes(Parser::from_reader(reader))
+    }
+
+    /// Looks up a mapping's value by key, or a sequence's entry by position
+    pub fn get<I: ValueIndex>(&self, index: I) -> Option<&Value> {
+        index.index_into(self)
+    }
+
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Int(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    /// Integers are converted, possibly losing precision
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Float(f) => Some(*f),
+            Self::Int(i) => Some(*i as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_sequence(&self) -> Option<&Sequence> {
+        match self {
+            Self::Sequence(seq) => Some(seq),
+            _ => None,
+        }
+    }
+
+    pub fn as_sequence_mut(&mut self) -> Option<&mut Sequence> {
+        match self {
+            Self::Sequence(seq) => Some(seq),
+            _ => None,
+        }
+    }
+
+    pub fn as_mapping(&self) -> Option<&Mapping> {
+        match self {
+            Self::Mapping(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_mapping_mut(&mut self) -> Option<&mut Mapping> {
+        match self {
+            Self::Mapping(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_tagged(&self) -> Option<&Tagged> {
+        match self {
+            Self::Tagged(tagged) => Some(tagged),
+            _ => None,
+        }
+    }
+
+    /// Builds a scalar from its content and what it resolved to
+    fn scalar(resolved: Resolved, value: Cow<str>) -> Self {
+        match resolved {
+            Resolved::Null => Self::Null,
+            Resolved::Bool(b) => Self::Bool(b),
+            Resolved::Int(i) => Self::Int(i),
+            Resolved::UInt(u) => Self::Float(u as f64),
+            Resolved::Float(f) => Self::Float(f),
+            Resolved::Str | Resolved::Merge => Self::String(value.into_owned()),
+        }
+    }
+}
+
+impl FromStr for Value {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::from_nodes(Parser::from_str(s))
+    }
+}
+
+/// Missing keys and out of bounds positions index to null,
+/// tagged values are indexed through
+impl<I: ValueIndex> ops::Index<I> for Value {
+    type Output = Value;
+
+    fn index(&self, index: I) -> &Value {
+        index.index_into(self).unwrap_or(&NULL)
+    }
+}
+
+/// Types which can index into a [`Value`], strings look up
+/// mapping keys and integers sequence entries
+pub trait ValueIndex {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value>;
+}
+
+impl ValueIndex for usize {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        match value {
+            Value::Sequence(seq) => seq.get(*self),
+            Value::Tagged(tagged) => self.index_into(&tagged.value),
+            _ => None,
+        }
+    }
+}
+
+impl ValueIndex for str {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        match value {
+            Value::Mapping(map) => map.get(self),
+            Value::Tagged(tagged) => self.index_into(&tagged.value),
+            _ => None,
+        }
+    }
+}
+
+impl ValueIndex for String {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        self.as_str().index_into(value)
+    }
+}
+
+impl<T: ?Sized + ValueIndex> ValueIndex for &T {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        (**self).index_into(value)
+    }
+}
+
+impl Mapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get<K>(&self, key: &K) -> Option<&Value>
+    where
+        K: ?Sized,
+        Value: PartialEq<K>,
+    {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn get_mut<K>(&mut self, key: &K) -> Option<&mut Value>
+    where
+        K: ?Sized,
+        Value: PartialEq<K>,
+    {
+        self.entries
+            .iter_mut()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn contains_key<K>(&self, key: &K) -> bool
+    where
+        K: ?Sized,
+        Value: PartialEq<K>,
+    {
+        self.get(key).is_some()
+    }
+
+    /// Inserts the given entry, an existing entry with the same key
+    /// keeps its position and has its old value returned
+    pub fn insert(&mut self, key: impl Into<Value>, value: impl Into<Value>) -> Option<Value> {
+        let (key, value) = (key.into(), value.into());
+
+        match self.get_mut(&key) {
+            Some(old) => Some(std::mem::replace(old, value)),
+            None => {
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    /// Removes the entry with the given key, keeping the order of the rest
+    pub fn remove<K>(&mut self, key: &K) -> Option<Value>
+    where
+        K: ?Sized,
+        Value: PartialEq<K>,
+    {
+        let position = self.entries.iter().position(|(k, _)| k == key)?;
+
+        Some(self.entries.remove(position).1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Value, &Value)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+}
+
+/// Mappings are equal when they hold the same entries, in any order
+impl PartialEq for Mapping {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl IntoIterator for Mapping {
+    type Item = (Value, Value);
+    type IntoIter = std::vec::IntoIter<(Value, Value)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<K: Into<Value>, V: Into<Value>> Extend<(K, V)> for Mapping {
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: Into<Value>, V: Into<Value>> FromIterator<(K, V)> for Mapping {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+
+        map
+    }
+}
+
+macro_rules! from_int {
+    ($($ty:ty)*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(n: $ty) -> Self {
+                    Self::Int(n.into())
+                }
+            }
+
+            impl PartialEq<$ty> for Value {
+                fn eq(&self, other: &$ty) -> bool {
+                    self.as_i64() == Some((*other).into())
+                }
+            }
+        )*
+    };
+}
+
+from_int! { i8 i16 i32 i64 u8 u16 u32 }
+
+/// Like [`from_int`], but for integers which may not fit an i64.
+/// Those that don't are converted to floats, as when parsed
+macro_rules! from_wide_int {
+    ($($ty:ty)*) => {
+        $(
+            impl From<$ty> for Value {
+                fn from(n: $ty) -> Self {
+                    i64::try_from(n).map_or(Self::Float(n as f64), Self::Int)
+                }
+            }
+
+            impl PartialEq<$ty> for Value {
+                fn eq(&self, other: &$ty) -> bool {
+                    match i64::try_from(*other) {
+                        Ok(n) => self.as_i64() == Some(n),
+                        Err(_) => matches!(self, Self::Float(f) if *f == *other as f64),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+from_wide_int! { u64 usize isize i128 u128 }
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Self::Bool(b)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(f: f32) -> Self {
+        Self::Float(f.into())
+    }
+}
+
+impl From<f64> for Value {
+    fn from(f: f64) -> Self {
+        Self::Float(f)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Self::String(s.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Self::String(s)
+    }
+}
+
+impl From<Cow<'_, str>> for Value {
+    fn from(s: Cow<'_, str>) -> Self {
+        Self::String(s.into_owned())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(option: Option<T>) -> Self {
+        option.map_or(Self::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(seq: Vec<T>) -> Self {
+        Self::Sequence(seq.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<Mapping> for Value {
+    fn from(map: Mapping) -> Self {
+        Self::Mapping(map)
+    }
+}
+
+impl From<Tagged> for Value {
+    fn from(tagged: Tagged) -> Self {
+        Self::Tagged(Box::new(tagged))
+    }
+}
+
+impl<T: Into<Value>> FromIterator<T> for Value {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::Sequence(iter.into_iter().map(Into::into).collect())
+    }
+}
+
+impl PartialEq<bool> for Value {
+    fn eq(&self, other: &bool) -> bool {
+        self.as_bool() == Some(*other)
+    }
+}
+
+impl PartialEq<f64> for Value {
+    fn eq(&self, other: &f64) -> bool {
+        matches!(self, Self::Float(f) if f == other)
+    }
+}
+
+impl PartialEq<str> for Value {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == Some(other)
+    }
+}
+
+impl PartialEq<&str> for Value {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == Some(*other)
+    }
+}
+
+impl PartialEq<String> for Value {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == Some(other.as_str())
+    }
+}
+
+/// Assembles values from the parser's nodes
+struct Builder<I, S> {
+    nodes: I,
+    schema: S,
+    // Position of the last node pulled
+    mark: Mark,
+}
+
+impl<'a, I, S> Builder<I, S>
+where
+    I: Iterator<Item = Result<Node<'a>>>,
+    S: Schema,
+{
+    fn stream(&mut self) -> Result<Value> {
+        self.expect(NodeKind::StreamStart)?;
+
+        let node = self.next()?;
+        let value = match node.kind {
+            NodeKind::DocumentStart(_) => self.document()?,
+            NodeKind::StreamEnd => return Ok(Value::Null),
+            kind => return Err(unexpected(&kind, "a document").at(node.mark)),
+        };
+
+        self.expect(NodeKind::StreamEnd)?;
+
+        Ok(value)
+    }
+
+    /// Builds the value of the document whose start was just pulled
+    fn document(&mut self) -> Result<Value> {
+        let node = self.next()?;
+        let value = self.value(node)?;
+        self.expect(NodeKind::DocumentEnd)?;
+
+        Ok(value)
+    }
+
+    /// Builds the value starting at the given node. Nodes with a tag
+    /// the schemas don't resolve are kept as [`Tagged`] values
+    fn value(&mut self, node: Node<'a>) -> Result<Value> {
+        let (mark, tag) = (node.mark, node.tag);
+        let value = match node.kind {
+            NodeKind::Scalar { value, style } => {
+                let plain = style == ScalarStyle::Plain;
+                let resolved = schema::resolve_tagged(&self.schema, tag.as_deref(), &value, plain)
+                    .map_err(|e| e.at(mark))?;
+
+                Value::scalar(resolved, value)
+            }
+            NodeKind::SequenceStart => {
+                schema::check_collection(tag.as_deref(), false).map_err(|e| e.at(mark))?;
+                let mut seq = Sequence::new();
+
+                loop {
+                    match self.next()? {
+                        node if node.kind == NodeKind::SequenceEnd => break,
+                        node => seq.push(self.value(node)?),
+                    }
+                }
+
+                Value::Sequence(seq)
+            }
+            NodeKind::MappingStart => {
+                schema::check_collection(tag.as_deref(), true).map_err(|e| e.at(mark))?;
+                let mut map = Mapping::new();
+
+                loop {
+                    match self.next()? {
+                        node if node.kind == NodeKind::MappingEnd => break,
+                        node => {
+                            let key_mark = node.mark;
+                            let key = self.value(node)?;
+
+                            if map.contains_key(&key) {
+                                return Err(Error::from(ErrorKind::DuplicateKey).at(key_mark));
+                            }
+
+                            let node = self.next()?;
+                            map.insert(key, self.value(node)?);
+                        }
+                    }
+                }
+
+                Value::Mapping(map)
+            }
+            kind => return Err(unexpected(&kind, "a node").at(mark)),
+        };
+
+        match tag {
+            Some(tag) if !schema::is_schema_tag(&tag) => Ok(Tagged {
+                tag: tag.into_owned(),
+                value,
+            }
+            .into()),
+            _ => Ok(value),
+        }
+    }
+
+    fn next(&mut self) -> Result<Node<'a>> {
+        loop {
+            match self.nodes.next() {
+                Some(Ok(Node {
+                    kind: NodeKind::Comment(_),
+                    ..
+                })) => continue,
+                Some(Ok(node)) => {
+                    self.mark = node.mark;
+
+                    return Ok(node);
+                }
+                Some(Err(e)) => return Err(e),
+                None => return Err(Error::from(ErrorKind::InvalidEOF).at(self.mark)),
+            }
+        }
+    }
+
+    fn expect(&mut self, kind: NodeKind) -> Result<()> {
+        let node = self.next()?;
+
+        if node.kind == kind {
+            Ok(())
+        } else {
+            Err(unexpected(&node.kind, kind.describe()).at(node.mark))
+        }
+    }
+}
+
+fn unexpected(kind: &NodeKind, expected: &str) -> Error {
+    let msg = format!("expected {}, found {}", expected, kind.describe());
+
+    ErrorKind::Message(msg.into()).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn value_nested() -> Result<()> {
+        let data = "name: oxiyaml
+version: 3
+ratio: .5
+enabled: TRUE
+owner: ~
+quoted: '42'
+tags: [a, b]
+nested:
+  - key: value
+  - 0x10
+";
+        let value: Value = data.parse()?;
+
+        assert_eq!(value["name"], "oxiyaml");
+        assert_eq!(value["version"], 3);
+        assert_eq!(value["ratio"], 0.5);
+        assert_eq!(value["enabled"], true);
+        assert!(value["owner"].is_null());
+        assert_eq!(value["quoted"].as_str(), Some("42"));
+        assert_eq!(value["tags"][1], "b");
+        assert_eq!(value["nested"][0]["key"], "value");
+        assert_eq!(value["nested"][1].as_f64(), Some(16.0));
+
+        // Missing entries index to null
+        assert!(value["missing"][0]["key"].is_null());
+        assert!(value["tags"][5].is_null());
+        assert_eq!(value.get("missing"), None);
+        assert_eq!(
+            value.get("tags").and_then(|tags| tags.get(0)),
+            Some(&"a".into())
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn value_ordered() -> Result<()> {
+        let value = Value::from_reader("z: 1\na: 2\nm: 3\n".as_bytes())?;
+        let map = value.as_mapping().unwrap();
+
+        assert_eq!(map.keys().collect::<Vec<_>>(), vec!["z", "a", "m"]);
+        assert_eq!(map.get("a"), Some(&Value::Int(2)));
+
+        let mut map = map.clone();
+        assert_eq!(map.insert("a", 4), Some(Value::Int(2)));
+        assert_eq!(map.remove("z"), Some(Value::Int(1)));
+        assert_eq!(map.insert("b", vec![1, 2]), None);
+        assert_eq!(
+            map.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("a".into(), 4.into()),
+                ("m".into(), 3.into()),
+                ("b".into(), vec![1, 2].into())
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn value_mapping_eq() -> Result<()> {
+        let value: Value = "a: 1\nb: [2]\n".parse()?;
+        let map: Mapping = vec![("b", Value::from(vec![2])), ("a", 1.into())]
+            .into_iter()
+            .collect();
+
+        assert_eq!(value, Value::from(map.clone()));
+
+        let mut other = map.clone();
+        other.insert("c", 3);
+        assert_ne!(map, other);
+        assert_ne!(other, map);
+
+        Ok(())
+    }
+
+    #[test]
+    fn value_duplicate_key() {
+        let invalid = ["a: 1\nb: 2\na: 3\n", "{a: 1, 'a': 2}", "1: x\n0x1: y\n"];
+
+        for data in invalid.iter() {
+            let err = data.parse::<Value>().unwrap_err();
+
+            assert!(matches!(err.kind(), ErrorKind::DuplicateKey), "{}", data);
+        }
+    }
+
+    #[test]
+    fn value_from() {
+        let map: Mapping = vec![("a", Value::from(1)), ("b", Value::from(None::<bool>))]
+            .into_iter()
+            .collect();
+        let value = Value::from(vec![Value::from(map), "text".into(), 1.5.into()]);
+
+        assert_eq!(value[0]["a"], 1);
+        assert!(value[0]["b"].is_null());
+        assert_eq!(value[1], "text");
+        assert_eq!(value[2], 1.5);
+        assert_eq!(Value::from(3u64), Value::Int(3));
+        assert_eq!(Value::from(3usize), 3usize);
+        assert_eq!(Value::from(-3i128), Value::Int(-3));
+        assert_eq!(Value::from(u64::MAX), Value::Float(u64::MAX as f64));
+        assert_eq!(Value::from(u64::MAX), u64::MAX);
+        assert_eq!(
+            (1..4).collect::<Value>(),
+            Value::Sequence(vec![Value::Int(1), Value::Int(2), Value::Int(3)])
+        );
+    }
+
+    #[test]
+    fn value_schema() -> Result<()> {
+        use crate::{Failsafe, Yaml11};
+
+        let data = "enabled: on\ntimeout: 1:30\nmode: 0644\n";
+        let legacy = Value::from_nodes_with(Parser::from_str(data), Yaml11)?;
+        let strings = Value::from_nodes_with(Parser::from_str(data), Failsafe)?;
+
+        assert_eq!(legacy["enabled"], true);
+        assert_eq!(legacy["timeout"], 90);
+        assert_eq!(legacy["mode"], 0o644);
+        assert_eq!(strings["mode"], "0644");
+        assert_eq!(data.parse::<Value>()?["enabled"], "on");
+
+        Ok(())
+    }
+
+    #[test]
+    fn value_aliases() -> Result<()> {
+        use crate::{Merge, Yaml11};
+
+        let data = include_str!("../testing/data/anchors.yaml");
+        let value = Value::from_nodes_with(Parser::from_str(data), Yaml11)?;
+
+        assert_eq!(value["build"]["image"], "rust:latest");
+        assert_eq!(value["build"]["cache"]["paths"][1], "~/.cargo");
+        assert_eq!(value["build"]["script"][0], "cargo build");
+        assert_eq!(value["test"]["retries"], 0);
+        assert_eq!(value["test"].as_mapping().map(|map| map.len()), Some(4));
+        // Earlier mappings take precedence when merging several
+        assert_eq!(value["lint"]["image"], "rust:latest");
+        assert_eq!(value["lint"]["allow_failure"], true);
+        assert_eq!(value["lint"]["after"], value["lint"]["script"]);
+        assert_eq!(
+            Value::from_nodes_with(Parser::from_str(data), Merge(Core))?["build"]["retries"],
+            2
+        );
+
+        // Merge keys are only applied under schemas which resolve them
+        let value: Value = data.parse()?;
+        assert_eq!(value["build"]["<<"]["retries"], 2);
+        assert_eq!(value["build"]["image"], Value::Null);
+
+        Ok(())
+    }
+
+    #[test]
+    fn value_merge_precedence() -> Result<()> {
+        use crate::Yaml11;
+
+        // Keys given explicitly win over merged ones holding the same value
+        let data = "b: &b {a: 1, 1: one, c: z}
+c:
+  <<: *b
+  \"a\": 2
+  0x1: first
+";
+        let value = Value::from_nodes_with(Parser::from_str(data), Yaml11)?;
+        let map = value["c"].as_mapping().unwrap();
+
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get("a"), Some(&Value::Int(2)));
+        assert_eq!(map.get(&Value::Int(1)), Some(&Value::from("first")));
+        assert_eq!(map.get("c"), Some(&Value::from("z")));
+
+        let data = "b: &b\n  ? [k]\n  : one\nc:\n  <<: *b\n  ? [k]\n  : two\n";
+        let value = Value::from_nodes_with(Parser::from_str(data), Yaml11)?;
+        let map = value["c"].as_mapping().unwrap();
+
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(&Value::from(vec!["k"])), Some(&Value::from("two")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn value_aliases_invalid() {
+        use crate::Yaml11;
+
+        let err = "a: &a 1\nb: *c\n".parse::<Value>().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::UnknownAnchor));
+        assert_eq!(err.mark().map(|mark| mark.line), Some(1));
+
+        // An anchor can't be referred to from inside its own node
+        let err = "a: &a [*a]\n".parse::<Value>().unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::UnknownAnchor));
+
+        let err = Value::from_nodes_with(Parser::from_str("a:\n  <<: [b]\n"), Yaml11).unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::InvalidMerge));
+        assert_eq!(err.mark().map(|mark| mark.line), Some(1));
+    }
+
+    #[test]
+    fn value_tags() -> Result<()> {
+        let data = "%TAG !e! tag:example.com,2000:
+---
+str: !!str 123
+float: !!float 1
+quoted: !!int '0x10'
+plain: ! true
+local: !celsius 21.5
+global: !e!point {x: 1, y: 2}
+";
+        let value: Value = data.parse()?;
+
+        assert_eq!(value["str"], "123");
+        assert_eq!(value["float"], Value::Float(1.0));
+        assert_eq!(value["quoted"], 16);
+        assert_eq!(value["plain"], "true");
+        assert_eq!(
+            value["local"].as_tagged(),
+            Some(&Tagged {
+                tag: "!celsius".into(),
+                value: Value::Float(21.5),
+            })
+        );
+        assert_eq!(
+            value["global"]
+                .as_tagged()
+                .map(|tagged| tagged.tag.as_str()),
+            Some("tag:example.com,2000:point")
+        );
+        assert_eq!(value["global"].as_tagged().unwrap().value["y"], 2);
+        assert_eq!(value["global"]["y"], 2);
+
+        // Keys tagged with anything but !!merge are never merge keys
+        let value = Value::from_nodes_with(Parser::from_str("!!str <<: {a: b}\n"), crate::Yaml11)?;
+        assert!(value["<<"].as_mapping().is_some());
+
+        // While !!merge makes one, even without a schema resolving '<<'
+        let value: Value = "!!merge <<: {a: b}\nc: d\n".parse()?;
+        assert_eq!(value["a"], "b");
+        assert_eq!(value["c"], "d");
+        assert_eq!(value.as_mapping().map(|map| map.len()), Some(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn value_tags_invalid() {
+        let invalid = [
+            "a: !!int x\n",
+            "!!bool 1\n",
+            "- !!map [a]\n",
+            "!!seq {a: b}\n",
+        ];
+
+        for data in invalid.iter() {
+            let err = data.parse::<Value>().unwrap_err();
+
+            assert!(matches!(err.kind(), ErrorKind::InvalidTag), "{}", data);
+            assert!(err.mark().is_some());
+        }
+    }
+
+    #[test]
+    fn value_complex_keys() -> Result<()> {
+        let data = include_str!("../testing/data/map-explicit.yaml");
+        let value: Value = data.parse()?;
+        let map = value.as_mapping().unwrap();
+
+        let key = Value::from(vec!["sequence", "key"]);
+        assert_eq!(map.get(&key).unwrap()["other"], "entry");
+
+        let key: Mapping = vec![("nested", Value::from("mapping"))]
+            .into_iter()
+            .collect();
+        assert_eq!(map.get(&Value::from(key)).unwrap()["explicit"], "again");
+        assert_eq!(value["a multi line key"][1], "sequence");
+        assert_eq!(value["implicit"], "too");
+
+        Ok(())
+    }
+
+    #[test]
+    fn value_empty() -> Result<()> {
+        assert_eq!(Value::from_slice(b"")?, Value::Null);
+        assert_eq!("{}".parse::<Value>()?, Value::Mapping(Mapping::new()));
+
+        let value: Value = "a:\nb:\n  -\n  - !!str\n".parse()?;
+        assert!(value["a"].is_null());
+        assert!(value["b"][0].is_null());
+        assert_eq!(value["b"][1], "");
+
+        Ok(())
+    }
+
+    #[test]
+    fn value_invalid() {
+        use crate::Category;
+
+        let err = "a: [1, 2\n".parse::<Value>().unwrap_err();
+
+        assert!(err.mark().is_some());
+
+        let nodes = vec![Ok(NodeKind::MappingEnd.at(Mark::default()))];
+        let err = Value::from_nodes(nodes).unwrap_err();
+
+        assert_eq!(err.categorize(), Category::Data);
+    }
+}